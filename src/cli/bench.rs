@@ -0,0 +1,314 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Barrier};
+use std::time::{Duration, Instant};
+
+use rabc::{ErrorKind, RabcError};
+use serde::Serialize;
+
+use crate::output::{print_output, CliOutput};
+use crate::CliContext;
+
+/// Upper bounds in milliseconds of the latency histogram buckets, the
+/// last bucket holds everything slower.
+const LATENCY_BUCKETS_MS: [f64; 13] = [
+    0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0,
+    1000.0,
+];
+const HISTOGRAM_BAR_WIDTH: u64 = 40;
+/// Default requests per second of each client, half of the default
+/// `--max-message-rate` of rabcd so the requests are not throttled.
+pub(crate) const DEFAULT_RATE: u32 = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BenchOptions {
+    pub(crate) clients: u32,
+    pub(crate) duration: Duration,
+    /// Requests per second of each client, 0 means unlimited. Requests
+    /// over the `--max-message-rate` of rabcd, 100 by default, are
+    /// refused as `Throttled`.
+    pub(crate) rate: u32,
+}
+
+#[derive(Debug, Default)]
+struct ClientResult {
+    sent: u64,
+    latencies: Vec<Duration>,
+    errors: BTreeMap<String, u64>,
+    last_error: Option<RabcError>,
+}
+
+impl ClientResult {
+    fn add_error(&mut self, error: RabcError) {
+        *self.errors.entry(error.kind().to_string()).or_default() += 1;
+        self.last_error = Some(error);
+    }
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub(crate) struct LatencyStatistics {
+    pub(crate) min_ms: f64,
+    pub(crate) avg_ms: f64,
+    pub(crate) p50_ms: f64,
+    pub(crate) p90_ms: f64,
+    pub(crate) p99_ms: f64,
+    pub(crate) max_ms: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct HistogramBucket {
+    /// Upper bound of this bucket in milliseconds, `None` for the last
+    /// bucket
+    pub(crate) le_ms: Option<f64>,
+    pub(crate) count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct BenchOutput {
+    request: String,
+    clients: u32,
+    rate: u32,
+    duration_secs: f64,
+    sent: u64,
+    received: u64,
+    /// Replies per second
+    throughput: f64,
+    latency: LatencyStatistics,
+    histogram: Vec<HistogramBucket>,
+    /// Count of errors indexed by [ErrorKind]
+    errors: BTreeMap<String, u64>,
+}
+
+impl CliOutput for BenchOutput {
+    fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!(
+                "--- rabcd bench: {} clients, {} rate, {:.3} seconds ---",
+                self.clients,
+                if self.rate == 0 {
+                    "unlimited".to_string()
+                } else {
+                    format!("{}/s", self.rate)
+                },
+                self.duration_secs
+            ),
+            format!(
+                "{} requests sent, {} replies received, {:.1} replies/s",
+                self.sent, self.received, self.throughput
+            ),
+            format!(
+                "latency min/avg/max = {:.3}/{:.3}/{:.3} ms",
+                self.latency.min_ms, self.latency.avg_ms, self.latency.max_ms
+            ),
+            format!(
+                "latency p50/p90/p99 = {:.3}/{:.3}/{:.3} ms",
+                self.latency.p50_ms, self.latency.p90_ms, self.latency.p99_ms
+            ),
+            "latency histogram:".to_string(),
+        ];
+        let max_count =
+            self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+        for bucket in &self.histogram {
+            let bar_len = (bucket.count * HISTOGRAM_BAR_WIDTH)
+                .checked_div(max_count)
+                .unwrap_or(0);
+            let bound = match bucket.le_ms {
+                Some(le) => format!("<= {} ms", le),
+                None => "> 1000 ms".to_string(),
+            };
+            lines.push(format!(
+                "  {:>11} {:>9} {}",
+                bound,
+                bucket.count,
+                "#".repeat(bar_len as usize)
+            ));
+        }
+        if self.errors.is_empty() {
+            lines.push("errors: none".to_string());
+        } else {
+            lines.push("errors:".to_string());
+            for (kind, count) in &self.errors {
+                lines.push(format!("  {:<20} {}", kind, count));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Send `request` from multiple concurrent connections for specified
+/// duration and print the throughput, latency and errors.
+pub(crate) fn bench(
+    ctx: &CliContext,
+    options: BenchOptions,
+    request: String,
+) -> Result<(), RabcError> {
+    if options.clients == 0 {
+        return Err(RabcError::new(
+            ErrorKind::InvalidArgument,
+            "The count of clients should be bigger than 0".to_string(),
+        ));
+    }
+    // Start sending requests after all clients connected
+    let barrier = Arc::new(Barrier::new(options.clients as usize + 1));
+    let mut handles = Vec::new();
+    for _ in 0..options.clients {
+        let ctx = ctx.clone();
+        let barrier = barrier.clone();
+        let request = request.clone();
+        handles.push(std::thread::spawn(move || {
+            run_client(&ctx, options, &request, &barrier)
+        }));
+    }
+    barrier.wait();
+    let start = Instant::now();
+
+    let mut latencies = Vec::new();
+    let mut errors: BTreeMap<String, u64> = BTreeMap::new();
+    let mut sent = 0u64;
+    let mut last_error = None;
+    for handle in handles {
+        let result = handle.join().map_err(|_| {
+            RabcError::new(ErrorKind::Bug, "Bench client panicked".to_string())
+        })?;
+        sent += result.sent;
+        latencies.extend(result.latencies);
+        for (kind, count) in result.errors {
+            *errors.entry(kind).or_default() += count;
+        }
+        if result.last_error.is_some() {
+            last_error = result.last_error;
+        }
+    }
+    let duration_secs = start.elapsed().as_secs_f64();
+    if let (true, Some(e)) = (latencies.is_empty(), last_error) {
+        return Err(e);
+    }
+
+    latencies.sort_unstable();
+    let output = BenchOutput {
+        request,
+        clients: options.clients,
+        rate: options.rate,
+        duration_secs,
+        sent,
+        received: latencies.len() as u64,
+        throughput: latencies.len() as f64 / duration_secs,
+        latency: latency_statistics(&latencies),
+        histogram: latency_histogram(&latencies),
+        errors,
+    };
+    print_output(ctx.output, &output)
+}
+
+fn run_client(
+    ctx: &CliContext,
+    options: BenchOptions,
+    request: &str,
+    barrier: &Barrier,
+) -> ClientResult {
+    let mut result = ClientResult::default();
+    let conn = ctx.connect();
+    barrier.wait();
+    let mut conn = match conn {
+        Ok(c) => c,
+        Err(e) => {
+            result.add_error(e);
+            return result;
+        }
+    };
+    let start = Instant::now();
+    let interval = if options.rate == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs(1) / options.rate
+    };
+    while start.elapsed() < options.duration {
+        // Schedule by request count so slow replies do not lower the rate
+        let scheduled = start
+            + interval
+                .saturating_mul(u32::try_from(result.sent).unwrap_or(u32::MAX));
+        let now = Instant::now();
+        if scheduled > now {
+            std::thread::sleep(scheduled - now);
+        }
+        let request_start = Instant::now();
+        result.sent += 1;
+        match conn.request(request) {
+            Ok(_) => result.latencies.push(request_start.elapsed()),
+            Err(e) => {
+                let kind = e.kind();
+                result.add_error(e);
+                // The connection is not usable any more
+                if kind.is_connection_error() {
+                    break;
+                }
+                // Late reply of the timed out request would be taken as
+                // the reply of next request, hence use a new connection.
+                if kind == ErrorKind::Timeout {
+                    match ctx.connect() {
+                        Ok(c) => conn = c,
+                        Err(e) => {
+                            result.add_error(e);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Nearest-rank percentile of sorted durations in milliseconds.
+pub(crate) fn percentile_ms(sorted: &[Duration], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    to_ms(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+pub(crate) fn latency_statistics(sorted: &[Duration]) -> LatencyStatistics {
+    if sorted.is_empty() {
+        return LatencyStatistics::default();
+    }
+    LatencyStatistics {
+        min_ms: to_ms(sorted[0]),
+        avg_ms: sorted.iter().copied().map(to_ms).sum::<f64>()
+            / sorted.len() as f64,
+        p50_ms: percentile_ms(sorted, 50.0),
+        p90_ms: percentile_ms(sorted, 90.0),
+        p99_ms: percentile_ms(sorted, 99.0),
+        max_ms: to_ms(sorted[sorted.len() - 1]),
+    }
+}
+
+pub(crate) fn latency_histogram(
+    latencies: &[Duration],
+) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = LATENCY_BUCKETS_MS
+        .iter()
+        .map(|le| HistogramBucket {
+            le_ms: Some(*le),
+            count: 0,
+        })
+        .chain(std::iter::once(HistogramBucket {
+            le_ms: None,
+            count: 0,
+        }))
+        .collect();
+    for latency in latencies {
+        let ms = to_ms(*latency);
+        let index = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|le| ms <= *le)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+fn to_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::{RabcError, RabcLogRecord, RabcMessage};
+
+use crate::output::{print_stream_item, CliOutput};
+use crate::CliContext;
+
+impl CliOutput for RabcLogRecord {
+    fn to_text(&self) -> String {
+        let mut line = format!(
+            "[{} {:<5} {}] {}",
+            self.time, self.level, self.target, self.msg
+        );
+        for (key, value) in &self.fields {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        line
+    }
+}
+
+/// Print daemon logs up to specified level until connection closed.
+pub(crate) fn logs(
+    ctx: &CliContext,
+    level: log::LevelFilter,
+) -> Result<(), RabcError> {
+    let mut conn = ctx.connect()?;
+    conn.request(&format!("logs {}", level.as_str().to_lowercase()))?;
+    // Log records could arrive at any time
+    conn.set_timeout(None)?;
+    loop {
+        match conn.ipc_recv_message()? {
+            RabcMessage::Log(record) => {
+                print_stream_item(ctx.output, &record)?;
+            }
+            RabcMessage::Error(e) => return Err(e),
+            message @ RabcMessage::SetLogLevel(_) => {
+                message.apply_log();
+            }
+            message => {
+                log::debug!("Ignoring unexpected message {:?}", message);
+            }
+        }
+    }
+}
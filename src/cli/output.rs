@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::Write;
+
+use clap::ValueEnum;
+use rabc::{ErrorKind, RabcError};
+use serde::Serialize;
+
+/// Exit code for errors not listed in [exit_code()].
+pub(crate) const EXIT_CODE_UNKNOWN: u8 = 1;
+
+/// Help text of exit codes, should be kept in sync with [exit_code()].
+pub(crate) const EXIT_CODE_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Unknown error
+  2  Invalid command line
+  3  IpcConnectionError
+  4  ExceededIpcMaxSize
+  5  InvalidArgument
+  6  Bug
+  7  Throttled
+  8  PermissionDenied
+  9  Timeout
+  10 NotConnected
+  11 ProtocolError
+  12 PeerClosed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    Text,
+    Json,
+    Yaml,
+}
+
+/// Command output which could be printed in all [OutputFormat].
+/// The serialized form is the stable machine-readable schema, please do not
+/// rename or remove existing fields.
+pub(crate) trait CliOutput: Serialize {
+    fn to_text(&self) -> String;
+}
+
+/// Schema of the error printed in JSON or YAML output.
+#[derive(Debug, Serialize)]
+struct ErrorOutput<'a> {
+    error: &'a RabcError,
+}
+
+impl CliOutput for ErrorOutput<'_> {
+    fn to_text(&self) -> String {
+        format!("Error: {}", self.error)
+    }
+}
+
+/// Exit code of specified error kind, please append new kinds instead of
+/// changing existing ones as scripts depend on them.
+pub(crate) fn exit_code(kind: ErrorKind) -> u8 {
+    match kind {
+        ErrorKind::IpcConnectionError => 3,
+        ErrorKind::ExceededIpcMaxSize => 4,
+        ErrorKind::InvalidArgument => 5,
+        ErrorKind::Bug => 6,
+        ErrorKind::Throttled => 7,
+        ErrorKind::PermissionDenied => 8,
+        ErrorKind::Timeout => 9,
+        ErrorKind::NotConnected => 10,
+        ErrorKind::ProtocolError => 11,
+        ErrorKind::PeerClosed => 12,
+        _ => EXIT_CODE_UNKNOWN,
+    }
+}
+
+pub(crate) fn print_output<T>(
+    format: OutputFormat,
+    output: &T,
+) -> Result<(), RabcError>
+where
+    T: CliOutput,
+{
+    let data = match format {
+        OutputFormat::Text => format!("{}\n", output.to_text()),
+        OutputFormat::Json => {
+            format!("{}\n", serde_json::to_string_pretty(output)?)
+        }
+        OutputFormat::Yaml => to_yaml(output)?,
+    };
+    write_stdout(&data)
+}
+
+/// Print one item of a stream, JSON items are printed one per line while
+/// YAML items are printed as separate documents.
+pub(crate) fn print_stream_item<T>(
+    format: OutputFormat,
+    output: &T,
+) -> Result<(), RabcError>
+where
+    T: CliOutput,
+{
+    let data = match format {
+        OutputFormat::Text => format!("{}\n", output.to_text()),
+        OutputFormat::Json => format!("{}\n", serde_json::to_string(output)?),
+        OutputFormat::Yaml => format!("---\n{}", to_yaml(output)?),
+    };
+    write_stdout(&data)
+}
+
+/// Print error to stdout in JSON or YAML format, or to stderr in text
+/// format.
+pub(crate) fn print_error(format: OutputFormat, error: &RabcError) {
+    let output = ErrorOutput { error };
+    if format == OutputFormat::Text {
+        write_stderr(&format!("{}\n", output.to_text()));
+    } else if let Err(e) = print_output(format, &output) {
+        write_stderr(&format!("{}\n{}\n", output.to_text(), e));
+    }
+}
+
+fn to_yaml<T>(output: &T) -> Result<String, RabcError>
+where
+    T: Serialize,
+{
+    serde_yaml::to_string(output).map_err(|e| {
+        RabcError::new(
+            ErrorKind::Bug,
+            format!("Failed to serialize output to YAML: {}", e),
+        )
+    })
+}
+
+// Unlike `println!()`, this does not panic when stdout is closed, for
+// example piped to `head`.
+pub(crate) fn write_stdout(data: &str) -> Result<(), RabcError> {
+    let mut stdout = std::io::stdout().lock();
+    match stdout
+        .write_all(data.as_bytes())
+        .and_then(|_| stdout.flush())
+    {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+            std::process::exit(0)
+        }
+        Err(e) => Err(RabcError::new(
+            ErrorKind::Bug,
+            format!("Failed to write to stdout: {}", e),
+        )),
+        Ok(()) => Ok(()),
+    }
+}
+
+// Unlike `eprintln!()`, this does not panic when stderr is closed.
+pub(crate) fn write_stderr(data: &str) {
+    std::io::stderr().lock().write_all(data.as_bytes()).ok();
+}
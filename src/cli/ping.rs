@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use rabc::{ErrorKind, RabcError};
+use serde::Serialize;
+
+use crate::output::{
+    exit_code, print_output, print_stream_item, write_stderr, CliOutput,
+    OutputFormat,
+};
+use crate::CliContext;
+
+#[derive(Debug, Clone, Serialize)]
+struct PingReply {
+    seq: u32,
+    reply: String,
+    rtt_ms: f64,
+}
+
+impl CliOutput for PingReply {
+    fn to_text(&self) -> String {
+        format!(
+            "{} from rabcd: seq={} time={:.3} ms",
+            self.reply, self.seq, self.rtt_ms
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PingError {
+    seq: u32,
+    error: RabcError,
+}
+
+impl CliOutput for PingError {
+    fn to_text(&self) -> String {
+        format!("Error: seq={} {}", self.seq, self.error)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct PingStatistics {
+    sent: u32,
+    received: u32,
+    errors: u32,
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct PingOutput {
+    replies: Vec<PingReply>,
+    errors: Vec<PingError>,
+    statistics: PingStatistics,
+}
+
+impl CliOutput for PingOutput {
+    fn to_text(&self) -> String {
+        self.statistics.to_text()
+    }
+}
+
+impl PingStatistics {
+    fn to_text(&self) -> String {
+        format!(
+            "--- rabcd ping statistics ---\n\
+             {} requests sent, {} replies received, {} errors\n\
+             rtt min/avg/max = {:.3}/{:.3}/{:.3} ms",
+            self.sent,
+            self.received,
+            self.errors,
+            self.min_ms,
+            self.avg_ms,
+            self.max_ms
+        )
+    }
+}
+
+/// Last item of the stream printed when pinging forever in JSON or YAML
+/// format.
+#[derive(Debug, Serialize)]
+struct PingSummary<'a> {
+    statistics: &'a PingStatistics,
+}
+
+impl CliOutput for PingSummary<'_> {
+    fn to_text(&self) -> String {
+        self.statistics.to_text()
+    }
+}
+
+/// Send `count` pings with specified interval, 0 `count` means forever.
+/// Failed requests are included in the output, the exit code is decided
+/// by the last error. The replies and errors are printed once received in
+/// text format or when pinging forever, otherwise they are printed with
+/// the statistics at the end.
+pub(crate) fn ping(
+    ctx: &CliContext,
+    count: u32,
+    interval: Duration,
+) -> Result<ExitCode, RabcError> {
+    let stream = count == 0 || ctx.output == OutputFormat::Text;
+    let mut conn = ctx.connect()?;
+    let mut output = PingOutput::default();
+    let mut rtt_sum_ms = 0.0;
+    let mut last_error_kind = None;
+    let mut seq = 0u32;
+    while count == 0 || seq < count {
+        if seq > 0 {
+            std::thread::sleep(interval);
+        }
+        seq += 1;
+        let start = Instant::now();
+        output.statistics.sent += 1;
+        let error = match conn.request("ping") {
+            Ok(reply) => {
+                let reply = PingReply {
+                    seq,
+                    reply,
+                    rtt_ms: start.elapsed().as_secs_f64() * 1000.0,
+                };
+                let stats = &mut output.statistics;
+                if stats.received == 0 || reply.rtt_ms < stats.min_ms {
+                    stats.min_ms = reply.rtt_ms;
+                }
+                stats.max_ms = stats.max_ms.max(reply.rtt_ms);
+                stats.received += 1;
+                rtt_sum_ms += reply.rtt_ms;
+                if stream {
+                    print_stream_item(ctx.output, &reply)?;
+                } else {
+                    output.replies.push(reply);
+                }
+                continue;
+            }
+            Err(e) => e,
+        };
+        let kind = error.kind();
+        let mut errors = vec![error];
+        // Late reply of the timed out request would be taken as the reply
+        // of next request, hence start over with a new connection.
+        if kind == ErrorKind::Timeout {
+            match ctx.connect() {
+                Ok(c) => conn = c,
+                Err(e) => errors.push(e),
+            }
+        }
+        // The connection is not usable any more
+        let stop = kind.is_connection_error() || errors.len() > 1;
+        for error in errors {
+            last_error_kind = Some(error.kind());
+            output.statistics.errors += 1;
+            let error = PingError { seq, error };
+            if ctx.output == OutputFormat::Text {
+                write_stderr(&format!("{}\n", error.to_text()));
+            } else if stream {
+                print_stream_item(ctx.output, &error)?;
+            } else {
+                output.errors.push(error);
+            }
+        }
+        if stop {
+            break;
+        }
+    }
+    if output.statistics.received > 0 {
+        output.statistics.avg_ms =
+            rtt_sum_ms / f64::from(output.statistics.received);
+    }
+    if stream && ctx.output != OutputFormat::Text {
+        print_stream_item(
+            ctx.output,
+            &PingSummary {
+                statistics: &output.statistics,
+            },
+        )?;
+    } else {
+        print_output(ctx.output, &output)?;
+    }
+    Ok(match last_error_kind {
+        Some(kind) => ExitCode::from(exit_code(kind)),
+        None => ExitCode::SUCCESS,
+    })
+}
@@ -1,28 +1,323 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use rabc::RabcClient;
-
-const WAIT_TIME: u32 = 10;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    init_logger();
-    let mut client = RabcClient::new()?;
-    for _ in 0..10 {
-        let events = client.poll(WAIT_TIME)?;
-        println!("Got events {:?}", events);
-        for event in events {
-            log::debug!("Got event {}", event);
-            if let Some(reply) = client.process(&event)? {
-                println!("Got reply from daemon {:?}", reply);
+mod bench;
+mod logs;
+mod output;
+mod ping;
+mod shell;
+mod status;
+mod unit_tests;
+mod watch;
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{ArgAction, Parser, Subcommand};
+use rabc::{RabcConnection, RabcError, SOCKET_PATH};
+use serde::Serialize;
+
+use crate::output::{
+    exit_code, print_error, print_output, CliOutput, OutputFormat,
+    EXIT_CODE_HELP,
+};
+
+#[derive(Parser, Debug)]
+#[command(
+    about = "Rabc command line client",
+    version,
+    after_help = EXIT_CODE_HELP
+)]
+struct Args {
+    /// UNIX socket path of rabc daemon
+    #[arg(long, global = true, value_name = "PATH", default_value = SOCKET_PATH)]
+    socket: String,
+    /// Timeout of each request, e.g. `500ms`, `5s`
+    #[arg(
+        long,
+        global = true,
+        default_value = "5s",
+        value_parser = humantime::parse_duration
+    )]
+    timeout: Duration,
+    /// Increase log verbosity, could be specified multiple times
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Decrease log verbosity, could be specified multiple times
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = ArgAction::Count,
+        conflicts_with = "verbose"
+    )]
+    quiet: u8,
+    /// Output format
+    #[arg(
+        short,
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text
+    )]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Send ping requests to daemon and show the round-trip time
+    Ping {
+        /// Stop after sending specified count of requests, 0 means forever
+        /// with the JSON or YAML output printed as stream of replies
+        #[arg(short, long, default_value_t = 4)]
+        count: u32,
+        /// Wait time between requests, e.g. `500ms`, `1s`
+        #[arg(
+            short,
+            long,
+            default_value = "1s",
+            value_parser = humantime::parse_duration
+        )]
+        interval: Duration,
+    },
+    /// Query the status of rabc daemon
+    Status {
+        /// Print the status in JSON, same as `--output json`
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print notifications from daemon until interrupted
+    Watch,
+    /// Print logs of daemon until interrupted
+    Logs {
+        /// Max level of logs: off, error, warn, info, debug or trace
+        #[arg(short, long, default_value = "info")]
+        level: log::LevelFilter,
+    },
+    /// Send raw request to daemon and print the reply
+    Send {
+        /// Command of the request
+        command: String,
+        /// Arguments of the command
+        args: Vec<String>,
+    },
+    /// Show version of rabcc and rabc daemon
+    Version,
+    /// Send requests from concurrent connections and report the
+    /// throughput, latency and errors
+    Bench {
+        /// Count of concurrent connections
+        #[arg(long, default_value_t = 10)]
+        clients: u32,
+        /// How long to send requests, e.g. `500ms`, `10s`
+        #[arg(
+            long,
+            default_value = "10s",
+            value_parser = humantime::parse_duration
+        )]
+        duration: Duration,
+        /// Requests per second of each connection, 0 means unlimited.
+        /// Daemon refuses requests over its `--max-message-rate`, 100 per
+        /// connection by default, as `Throttled` errors which are not
+        /// included in the latency, please raise daemon limit before
+        /// benchmarking higher rate
+        #[arg(long, default_value_t = bench::DEFAULT_RATE)]
+        rate: u32,
+        /// Request to send
+        #[arg(long, default_value = "ping")]
+        request: String,
+    },
+    /// Interactive prompt for sending requests to daemon on a single
+    /// connection
+    Shell,
+}
+
+/// Options shared by all commands.
+#[derive(Debug, Clone)]
+pub(crate) struct CliContext {
+    pub(crate) socket: String,
+    pub(crate) timeout: Duration,
+    pub(crate) output: OutputFormat,
+}
+
+impl CliContext {
+    pub(crate) fn connect(&self) -> Result<RabcConnection, RabcError> {
+        let mut conn = RabcConnection::connect_to(&self.socket)?;
+        conn.set_timeout(Some(self.timeout))?;
+        Ok(conn)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    // Shell has its own logger showing logs above the prompt
+    if !matches!(args.command, Command::Shell) {
+        init_logger(args.verbose, args.quiet);
+    }
+    let mut ctx = CliContext {
+        socket: args.socket,
+        timeout: args.timeout,
+        output: args.output,
+    };
+    let result = match args.command {
+        Command::Ping { count, interval } => ping::ping(&ctx, count, interval),
+        Command::Status { json } => {
+            if json {
+                ctx.output = OutputFormat::Json;
             }
+            status::status(&ctx).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Watch => watch::watch(&ctx).map(|_| ExitCode::SUCCESS),
+        Command::Logs { level } => {
+            logs::logs(&ctx, level).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Send { command, args } => {
+            send(&ctx, command, args).map(|_| ExitCode::SUCCESS)
+        }
+        Command::Version => version(&ctx).map(|_| ExitCode::SUCCESS),
+        Command::Bench {
+            clients,
+            duration,
+            rate,
+            request,
+        } => bench::bench(
+            &ctx,
+            bench::BenchOptions {
+                clients,
+                duration,
+                rate,
+            },
+            request,
+        )
+        .map(|_| ExitCode::SUCCESS),
+        Command::Shell => shell::shell(&ctx).map(|_| ExitCode::SUCCESS),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            print_error(ctx.output, &e);
+            ExitCode::from(exit_code(e.kind()))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SendOutput {
+    request: String,
+    /// Reply is included as JSON value if it is valid JSON
+    reply: serde_json::Value,
+}
+
+impl CliOutput for SendOutput {
+    fn to_text(&self) -> String {
+        match &self.reply {
+            serde_json::Value::String(s) => s.to_string(),
+            v => v.to_string(),
         }
     }
-    Ok(())
 }
 
-fn init_logger() {
-    let mut log_builder = env_logger::Builder::new();
-    // TODO: Support changing logging level
-    log_builder.filter(Some("rabc"), log::LevelFilter::Debug);
-    log_builder.init();
+fn send(
+    ctx: &CliContext,
+    command: String,
+    args: Vec<String>,
+) -> Result<(), RabcError> {
+    let request = std::iter::once(command)
+        .chain(args)
+        .collect::<Vec<String>>()
+        .join(" ");
+    let mut conn = ctx.connect()?;
+    let reply = conn.request(&request)?;
+    let reply = serde_json::from_str(&reply)
+        .unwrap_or(serde_json::Value::String(reply));
+    print_output(ctx.output, &SendOutput { request, reply })
+}
+
+#[derive(Debug, Serialize)]
+struct VersionOutput {
+    client: String,
+    /// `None` if daemon is not reachable
+    daemon: Option<String>,
+}
+
+impl CliOutput for VersionOutput {
+    fn to_text(&self) -> String {
+        format!(
+            "rabcc {}\nrabcd {}",
+            self.client,
+            self.daemon.as_deref().unwrap_or("unavailable")
+        )
+    }
+}
+
+fn version(ctx: &CliContext) -> Result<(), RabcError> {
+    let daemon = match ctx.connect().and_then(|mut c| c.request("version")) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::warn!("Failed to query daemon version: {}", e);
+            None
+        }
+    };
+    print_output(
+        ctx.output,
+        &VersionOutput {
+            client: env!("CARGO_PKG_VERSION").to_string(),
+            daemon,
+        },
+    )
+}
+
+/// Max level of specified log target. The logs of `rabc::` targets follow
+/// the level requested by daemon via the `client_log_level` command once
+/// requested.
+pub(crate) fn log_level_of(
+    target: &str,
+    level: log::LevelFilter,
+) -> log::LevelFilter {
+    match rabc::daemon_requested_log_level() {
+        Some(l) if target.starts_with("rabc::") => l,
+        _ => level,
+    }
+}
+
+/// Filter logs by [log_level_of()] and print them through env_logger.
+struct CliLogger {
+    inner: env_logger::Logger,
+    level: log::LevelFilter,
+}
+
+impl log::Log for CliLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log_level_of(metadata.target(), self.level)
+            && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+fn init_logger(verbose: u8, quiet: u8) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        i16::MIN..=-2 => log::LevelFilter::Off,
+        -1 => log::LevelFilter::Error,
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        2 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    // Level is checked by CliLogger as daemon might request higher level
+    let inner = env_logger::Builder::new()
+        .filter(Some("rabc"), log::LevelFilter::Trace)
+        .build();
+    if log::set_boxed_logger(Box::new(CliLogger { inner, level })).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
 }
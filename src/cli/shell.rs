@@ -0,0 +1,348 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nix::poll::{poll, PollFd, PollFlags};
+use rabc::{ErrorKind, RabcClient, RabcClientMessage, RabcError, RabcReply};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, ExternalPrinter, Helper};
+
+use crate::output::{write_stderr, write_stdout};
+use crate::{log_level_of, CliContext};
+
+const PROMPT: &str = "rabcc> ";
+const HISTORY_FILE_NAME: &str = ".rabcc_history";
+const DEFAULT_LOG_LEVEL: log::LevelFilter = log::LevelFilter::Info;
+
+const BUILTIN_HELP: &str = "\
+Requests are sent to daemon as is, replies and notifications are printed
+once received. Built-in commands:
+  .help                 Show this help
+  .log <level>          Show client logs of specified level: off, error,
+                        warn, info, debug or trace
+  .heartbeat <on|off>   Show round-trip time of the heartbeat pings
+  .exit                 Exit the shell, same as `exit`, `quit` or Ctrl-D";
+
+const BUILTINS: [&str; 6] =
+    [".help", ".log", ".heartbeat", ".exit", "exit", "quit"];
+
+type SharedPrinter = Mutex<Option<Box<dyn ExternalPrinter + Send>>>;
+
+// Once the editor is created, logs and daemon messages are printed above
+// the prompt through this printer.
+static PRINTER: SharedPrinter = Mutex::new(None);
+
+// Log level changed by `.log` command
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(DEFAULT_LOG_LEVEL as usize);
+
+fn log_level() -> log::LevelFilter {
+    log::LevelFilter::iter()
+        .nth(LOG_LEVEL.load(Ordering::Relaxed))
+        .unwrap_or(DEFAULT_LOG_LEVEL)
+}
+
+fn set_log_level(level: log::LevelFilter) {
+    LOG_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+fn print_line(msg: String) {
+    if let Ok(mut printer) = PRINTER.lock() {
+        if let Some(printer) = printer.as_mut() {
+            if printer.print(format!("{}\n", msg)).is_ok() {
+                return;
+            }
+        }
+    }
+    // Failure is ignored as this might be called by logger or events thread
+    write_stdout(&format!("{}\n", msg)).ok();
+}
+
+/// Unlike the logger of other commands, the shell shows client logs up to
+/// info level by default and the level could be changed by `.log` command
+/// or by daemon.
+struct ShellLogger;
+
+impl log::Log for ShellLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        // Skip logs of rustyline which could be emitted while printing
+        metadata.target().starts_with("rabc")
+            && metadata.level() <= log_level_of(metadata.target(), log_level())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            print_line(format!(
+                "[{} {}] {}",
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+struct ShellHelper {
+    commands: Vec<String>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let prefix = &line[..pos];
+        // Only the command name is completed
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = self
+            .commands
+            .iter()
+            .map(String::as_str)
+            .chain(BUILTINS)
+            .filter(|c| c.starts_with(prefix))
+            .map(|c| format!("{} ", c))
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Interactive prompt on a single persistent connection to daemon.
+pub(crate) fn shell(ctx: &CliContext) -> Result<(), RabcError> {
+    if log::set_boxed_logger(Box::new(ShellLogger)).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+
+    let mut client = RabcClient::new_with_socket(&ctx.socket)?;
+    let commands = match query_commands(&mut client, ctx.timeout) {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("Failed to query commands supported by daemon: {}", e);
+            Vec::new()
+        }
+    };
+
+    let mut editor =
+        Editor::<ShellHelper, DefaultHistory>::new().map_err(readline_error)?;
+    editor.set_helper(Some(ShellHelper { commands }));
+    let history_path =
+        std::env::var("HOME").map(|h| format!("{}/{}", h, HISTORY_FILE_NAME));
+    if let Ok(path) = history_path.as_ref() {
+        // The history file does not exist on first run
+        editor.load_history(path).ok();
+    }
+    match editor.create_external_printer() {
+        Ok(p) => {
+            if let Ok(mut printer) = PRINTER.lock() {
+                *printer = Some(Box::new(p));
+            }
+        }
+        Err(e) => log::debug!("Failed to create external printer: {}", e),
+    }
+
+    let fd = client.as_raw_fd();
+    let client = Arc::new(Mutex::new(client));
+    let show_heartbeat = Arc::new(AtomicBool::new(true));
+    {
+        let client = client.clone();
+        let show_heartbeat = show_heartbeat.clone();
+        std::thread::spawn(move || {
+            process_events(fd, &client, &show_heartbeat)
+        });
+    }
+
+    write_stdout(&format!(
+        "Connected to {}, type `.help` for help\n",
+        ctx.socket
+    ))?;
+    loop {
+        let line = match editor.readline(PROMPT) {
+            Ok(l) => l,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(readline_error(e)),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line).ok();
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(".exit" | "exit" | "quit"), _) => break,
+            (Some(".help"), _) => write_stdout(&format!("{}\n", BUILTIN_HELP))?,
+            (Some(".log"), Some(level)) => match level.parse() {
+                Ok(level) => set_log_level(level),
+                Err(_) => write_stderr(&format!(
+                    "Error: invalid log level '{}'\n",
+                    level
+                )),
+            },
+            (Some(".log"), None) => {
+                write_stdout(&format!("{}\n", log_level()))?
+            }
+            (Some(".heartbeat"), Some("on")) => {
+                show_heartbeat.store(true, Ordering::Relaxed)
+            }
+            (Some(".heartbeat"), Some("off")) => {
+                show_heartbeat.store(false, Ordering::Relaxed)
+            }
+            (Some(cmd), _) if cmd.starts_with('.') => write_stderr(&format!(
+                "Error: invalid built-in command '{}'\n",
+                line
+            )),
+            _ => {
+                let result = match client.lock() {
+                    Ok(mut c) => c.send(line),
+                    Err(_) => break,
+                };
+                if let Err(e) = result {
+                    write_stderr(&format!("Error: {}\n", e));
+                }
+            }
+        }
+    }
+    if let Ok(path) = history_path.as_ref() {
+        if let Err(e) = editor.save_history(path) {
+            log::debug!("Failed to save history to {}: {}", path, e);
+        }
+    }
+    Ok(())
+}
+
+// Print replies, notifications and heartbeats until connection closed.
+fn process_events(
+    fd: RawFd,
+    client: &Mutex<RabcClient>,
+    show_heartbeat: &AtomicBool,
+) {
+    loop {
+        // Wait without holding the lock so requests could be sent
+        let mut poll_fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        if let Err(e) = poll(&mut poll_fds, -1) {
+            if e != nix::errno::Errno::EINTR {
+                log::error!("Failed to poll client: {}", e);
+                return;
+            }
+            continue;
+        }
+        let mut client = match client.lock() {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        let events = match client.poll(0) {
+            Ok(e) => e,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+        for event in events {
+            match client.process_message(&event) {
+                Ok(Some(RabcClientMessage::Reply(reply))) => {
+                    print_reply(reply, show_heartbeat.load(Ordering::Relaxed))
+                }
+                Ok(Some(RabcClientMessage::Notification(n))) => {
+                    print_line(format!("Notification: {}", n))
+                }
+                Ok(_) => (),
+                Err(e) if e.kind().is_connection_error() => {
+                    print_line(format!("Error: {}", e));
+                    return;
+                }
+                Err(e) => print_line(format!("Error: {}", e)),
+            }
+        }
+    }
+}
+
+fn print_reply(reply: RabcReply, show_heartbeat: bool) {
+    let rtt_ms = reply.rtt.unwrap_or_default().as_secs_f64() * 1000.0;
+    if reply.heartbeat {
+        if show_heartbeat {
+            match reply.result {
+                Ok(_) => print_line(format!("Heartbeat: {:.3} ms", rtt_ms)),
+                Err(e) => print_line(format!("Heartbeat error: {}", e)),
+            }
+        }
+        return;
+    }
+    match reply.into_text() {
+        Ok(r) => {
+            let r = match serde_json::from_str::<serde_json::Value>(&r) {
+                Ok(
+                    v @ (serde_json::Value::Object(_)
+                    | serde_json::Value::Array(_)),
+                ) => serde_json::to_string_pretty(&v).unwrap_or(r),
+                _ => r,
+            };
+            print_line(r);
+            log::info!("Request took {:.3} ms", rtt_ms);
+        }
+        Err(e) => print_line(format!("Error: {}", e)),
+    }
+}
+
+// Query the command names advertised by daemon before the events thread
+// starts.
+fn query_commands(
+    client: &mut RabcClient,
+    timeout: Duration,
+) -> Result<Vec<String>, RabcError> {
+    client.send("commands")?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remain = deadline.saturating_duration_since(Instant::now());
+        if remain.is_zero() {
+            return Err(RabcError::new(
+                ErrorKind::Timeout,
+                "Timeout on waiting reply of 'commands'".to_string(),
+            ));
+        }
+        let mut poll_fds = [PollFd::new(client.as_raw_fd(), PollFlags::POLLIN)];
+        poll(&mut poll_fds, remain.as_millis().try_into().unwrap_or(-1))
+            .map_err(|e| {
+                RabcError::new(
+                    ErrorKind::Bug,
+                    format!("Failed to poll client: {}", e),
+                )
+            })?;
+        for event in client.poll(0)? {
+            if let Some(RabcClientMessage::Reply(reply)) =
+                client.process_message(&event)?
+            {
+                if reply.request.as_deref() == Some("commands") {
+                    return Ok(serde_json::from_str(&reply.into_text()?)?);
+                }
+            }
+        }
+    }
+}
+
+fn readline_error(e: ReadlineError) -> RabcError {
+    RabcError::new(ErrorKind::Bug, format!("Failed to read line: {}", e))
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use rabc::{RabcError, RabcStatus};
+
+use crate::output::{print_output, CliOutput};
+use crate::CliContext;
+
+pub(crate) fn status(ctx: &CliContext) -> Result<(), RabcError> {
+    let mut conn = ctx.connect()?;
+    let status = RabcStatus::from_json(&conn.request("status")?)?;
+    print_output(ctx.output, &status)
+}
+
+impl CliOutput for RabcStatus {
+    fn to_text(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut ret = String::new();
+
+        writeln!(ret, "Version:     {}", self.version).ok();
+        writeln!(ret, "Uptime:      {}", format_duration(self.uptime)).ok();
+        writeln!(
+            ret,
+            "Connections: {} (max {}, max per user {})",
+            self.connection_count,
+            format_limit(self.max_connections),
+            format_limit(self.max_connections_per_uid)
+        )
+        .ok();
+        writeln!(
+            ret,
+            "Rejected:    {} connections, {} throttled messages",
+            self.rejected_connection_count, self.throttled_message_count
+        )
+        .ok();
+        writeln!(ret).ok();
+        write!(
+            ret,
+            "{:>6} {:>8} {:>6} {:>12} {:>9} {:>9} {:>10} {:>10}",
+            "ID",
+            "PID",
+            "UID",
+            "CONNECTED",
+            "MSG_IN",
+            "MSG_OUT",
+            "BYTES_IN",
+            "BYTES_OUT"
+        )
+        .ok();
+        for client in self.clients.as_slice() {
+            write!(
+                ret,
+                "\n{:>6} {:>8} {:>6} {:>12} {:>9} {:>9} {:>10} {:>10}",
+                client.id,
+                client
+                    .pid
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                client.uid,
+                format_duration(now.saturating_sub(client.connect_time)),
+                client.messages_received,
+                client.messages_sent,
+                client.bytes_received,
+                client.bytes_sent
+            )
+            .ok();
+        }
+        ret
+    }
+}
+
+fn format_limit(limit: usize) -> String {
+    if limit == 0 {
+        "unlimited".to_string()
+    } else {
+        limit.to_string()
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let (hours, seconds) = (seconds / 3600, seconds % 3600);
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+    if days > 0 {
+        format!("{}d{}h{}m{}s", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{}h{}m{}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
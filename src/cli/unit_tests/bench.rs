@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use crate::bench::{latency_histogram, latency_statistics, percentile_ms};
+
+#[test]
+fn test_percentile_nearest_rank() {
+    let latencies: Vec<Duration> =
+        (1..=100).map(Duration::from_millis).collect();
+    assert_eq!(percentile_ms(&latencies, 50.0), 50.0);
+    assert_eq!(percentile_ms(&latencies, 90.0), 90.0);
+    assert_eq!(percentile_ms(&latencies, 99.0), 99.0);
+    assert_eq!(percentile_ms(&latencies, 100.0), 100.0);
+    assert_eq!(percentile_ms(&latencies[..1], 99.0), 1.0);
+    assert_eq!(percentile_ms(&[], 50.0), 0.0);
+}
+
+#[test]
+fn test_latency_statistics() {
+    let latencies = [
+        Duration::from_millis(1),
+        Duration::from_millis(2),
+        Duration::from_millis(3),
+        Duration::from_millis(10),
+    ];
+    let stats = latency_statistics(&latencies);
+    assert_eq!(stats.min_ms, 1.0);
+    assert_eq!(stats.avg_ms, 4.0);
+    assert_eq!(stats.p50_ms, 2.0);
+    assert_eq!(stats.p99_ms, 10.0);
+    assert_eq!(stats.max_ms, 10.0);
+}
+
+#[test]
+fn test_latency_histogram() {
+    let latencies = [
+        Duration::from_micros(50),
+        Duration::from_micros(100),
+        Duration::from_millis(2),
+        Duration::from_secs(2),
+    ];
+    let histogram = latency_histogram(&latencies);
+    assert_eq!(histogram[0].le_ms, Some(0.1));
+    assert_eq!(histogram[0].count, 2);
+    assert_eq!(histogram[4].le_ms, Some(2.5));
+    assert_eq!(histogram[4].count, 1);
+    let last = histogram.last().unwrap();
+    assert_eq!(last.le_ms, None);
+    assert_eq!(last.count, 1);
+    assert_eq!(histogram.iter().map(|b| b.count).sum::<u64>(), 4);
+}
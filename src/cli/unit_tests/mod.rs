@@ -0,0 +1,6 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(test)]
+mod bench;
+#[cfg(test)]
+mod output;
@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::ErrorKind;
+
+use crate::output::{exit_code, EXIT_CODE_HELP, EXIT_CODE_UNKNOWN};
+
+#[test]
+fn test_exit_code_distinct_per_error_kind() {
+    let mut codes: Vec<u8> =
+        ErrorKind::ALL.iter().map(|k| exit_code(*k)).collect();
+    codes.sort_unstable();
+    codes.dedup();
+    assert_eq!(codes.len(), ErrorKind::ALL.len());
+    // 0 is success, 2 is used by clap for invalid command line
+    assert!(!codes.contains(&0));
+    assert!(!codes.contains(&2));
+    assert!(!codes.contains(&EXIT_CODE_UNKNOWN));
+}
+
+#[test]
+fn test_exit_code_help_in_sync() {
+    for kind in ErrorKind::ALL {
+        let line = format!("{} {}", exit_code(kind), kind);
+        assert!(
+            EXIT_CODE_HELP.lines().any(|l| l
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                == line),
+            "Exit code help is missing '{}'",
+            line
+        );
+    }
+}
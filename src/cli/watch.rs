@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::{RabcError, RabcMessage, RabcNotification};
+
+use crate::output::{print_stream_item, CliOutput};
+use crate::CliContext;
+
+impl CliOutput for RabcNotification {
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// Print notifications from daemon until connection closed.
+pub(crate) fn watch(ctx: &CliContext) -> Result<(), RabcError> {
+    let mut conn = ctx.connect()?;
+    conn.request("subscribe")?;
+    // Notifications could arrive at any time
+    conn.set_timeout(None)?;
+    loop {
+        match conn.ipc_recv_message()?.apply_log() {
+            Some(RabcMessage::Notification(notification)) => {
+                print_stream_item(ctx.output, &notification)?;
+            }
+            Some(RabcMessage::Error(e)) => return Err(e),
+            None => (),
+            Some(message) => {
+                log::debug!("Ignoring unexpected message {:?}", message);
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+const HEADER_FILE: &str = "rabc.h";
+const VERSION_SCRIPT: &str = "rabc.map";
+// `<node> <symbol>` of exported functions, used by unit_tests/abi.rs
+const SYMBOLS_FILE: &str = "rabc.symbols";
+const CBINDGEN_CONFIG: &str = "cbindgen.toml";
+// cbindgen follows the `mod` of lib.rs for other source files
+const SOURCES: [&str; 4] = ["client.rs", "error.rs", "lib.rs", "logger.rs"];
+
+fn main() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = PathBuf::from(
+        std::env::var("OUT_DIR").expect("OUT_DIR not defined by cargo"),
+    );
+
+    for file in SOURCES.iter().chain(&[CBINDGEN_CONFIG, VERSION_SCRIPT]) {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+    println!(
+        "cargo:rustc-cdylib-link-arg=-Wl,--version-script={}",
+        crate_dir.join(VERSION_SCRIPT).display()
+    );
+    let nodes = version_nodes(&crate_dir);
+    gen_symver_asm(&crate_dir, &out_dir, &nodes);
+    gen_header(&crate_dir, &out_dir);
+}
+
+// Symbols of rabc.map as symbol name to version node
+fn version_nodes(crate_dir: &Path) -> BTreeMap<String, String> {
+    let content = std::fs::read_to_string(crate_dir.join(VERSION_SCRIPT))
+        .expect("Failed to read rabc.map");
+    let mut nodes = BTreeMap::new();
+    let mut node = "";
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line.strip_suffix(" {") {
+            node = name;
+        } else if let Some(symbol) = line.strip_suffix(';') {
+            if !symbol.starts_with('}') && symbol != "*" {
+                nodes.insert(symbol.to_string(), node.to_string());
+            }
+        }
+    }
+    nodes
+}
+
+// rustc links cdylib with its own anonymous version script which takes
+// precedence over the version nodes of ours, hence bind each symbol to its
+// node via `.symver` which only works in the object defining the symbol,
+// so each source file includes its own `symver_<file>.s`.
+// Fail the build if any `#[no_mangle]` function has no version node or
+// any symbol of rabc.map has no function.
+fn gen_symver_asm(
+    crate_dir: &Path,
+    out_dir: &Path,
+    nodes: &BTreeMap<String, String>,
+) {
+    let mut symbols = String::new();
+    let mut functions = BTreeSet::new();
+    for file in SOURCES {
+        let source = std::fs::read_to_string(crate_dir.join(file))
+            .expect("Failed to read source file");
+        let mut asm = String::new();
+        let mut lines = source.lines();
+        while let Some(line) = lines.next() {
+            if line != "#[no_mangle]" {
+                continue;
+            }
+            let symbol = lines
+                .next()
+                .and_then(|l| l.strip_prefix("pub extern \"C\" fn "))
+                .and_then(|l| l.split('(').next())
+                .unwrap_or_else(|| {
+                    panic!(
+                        "No `pub extern \"C\" fn` after #[no_mangle] in {file}"
+                    )
+                });
+            let node = nodes.get(symbol).unwrap_or_else(|| {
+                panic!("Function {symbol} of {file} is not in {VERSION_SCRIPT}")
+            });
+            asm.push_str(&format!(".symver {symbol}, {symbol}@@@{node}\n"));
+            symbols.push_str(&format!("{node} {symbol}\n"));
+            functions.insert(symbol.to_string());
+        }
+        let asm_file = format!("symver_{}.s", file.trim_end_matches(".rs"));
+        std::fs::write(out_dir.join(asm_file), asm)
+            .expect("Failed to write symver assembly");
+    }
+    for symbol in nodes.keys() {
+        if !functions.contains(symbol) {
+            panic!("Symbol {symbol} of {VERSION_SCRIPT} has no function");
+        }
+    }
+    std::fs::write(out_dir.join(SYMBOLS_FILE), symbols)
+        .expect("Failed to write rabc.symbols");
+}
+
+// The Makefile copies the header out of OUT_DIR
+fn gen_header(crate_dir: &Path, out_dir: &Path) {
+    let mut config =
+        cbindgen::Config::from_file(crate_dir.join(CBINDGEN_CONFIG))
+            .expect("Failed to load cbindgen.toml");
+    config.after_includes = Some(format!(
+        "\n#define RABC_VERSION_MAJOR {}\n\
+         #define RABC_VERSION_MINOR {}\n\
+         #define RABC_VERSION_MICRO {}\n\n\
+         #define RABC_VERSION \\\n    \
+         ((RABC_VERSION_MAJOR * 10000) + \\\n     \
+         (RABC_VERSION_MINOR * 100) + \\\n     \
+         RABC_VERSION_MICRO)",
+        env!("CARGO_PKG_VERSION_MAJOR"),
+        env!("CARGO_PKG_VERSION_MINOR"),
+        env!("CARGO_PKG_VERSION_PATCH"),
+    ));
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(crate_dir.join("lib.rs"))
+        .generate()
+        .expect("Failed to generate rabc.h")
+        .write_to_file(out_dir.join(HEADER_FILE));
+}
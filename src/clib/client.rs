@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::time::{Duration, Instant};
+
+use rabc::{
+    ErrorKind, RabcClient, RabcClientMessage, RabcError, RabcEvent,
+    RabcNotification, RabcReply,
+};
+
+use crate::error::RabcCError;
+use crate::{
+    c_string, call_with_error, catch_panic, reply_c_string, RABC_FAIL,
+    RABC_FAIL_NULL_POINTER, RABC_PASS,
+};
+
+// Symbol versions of rabc.map generated by build.rs
+#[cfg(not(test))]
+std::arch::global_asm!(include_str!(concat!(
+    env!("OUT_DIR"),
+    "/symver_client.s"
+)));
+
+/// Reply of request passed to the callback set by
+/// rabc_client_set_reply_callback(). The `request` is NULL if daemon sent
+/// error without request. Either `reply` or `err` is NULL. The strings and
+/// `err` are only valid during the callback.
+pub type RabcReplyCallback = Option<
+    extern "C" fn(
+        request: *const c_char,
+        reply: *const c_char,
+        err: *const RabcCError,
+        userdata: *mut c_void,
+    ),
+>;
+
+/// Notification in JSON, e.g. `{"event": "client_disconnected", "id": 1}`,
+/// passed to the callback set by rabc_client_set_notification_callback().
+/// The string is only valid during the callback.
+pub type RabcNotificationCallback =
+    Option<extern "C" fn(notification: *const c_char, userdata: *mut c_void)>;
+
+/// New `enum rabc_client_state` passed to the callback set by
+/// rabc_client_set_state_callback() with the error caused the change, the
+/// `err` is only valid during the callback.
+pub type RabcStateCallback = Option<
+    extern "C" fn(state: u32, err: *const RabcCError, userdata: *mut c_void),
+>;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RabcClientState {
+    Connected = 0,
+    Disconnected = 1,
+}
+
+pub struct RabcCClient {
+    pub(crate) client: RabcClient,
+    state: RabcClientState,
+    reply_callback: (RabcReplyCallback, *mut c_void),
+    notification_callback: (RabcNotificationCallback, *mut c_void),
+    state_callback: (RabcStateCallback, *mut c_void),
+}
+
+impl RabcCClient {
+    pub(crate) fn new(client: RabcClient) -> Self {
+        Self {
+            client,
+            state: RabcClientState::Connected,
+            reply_callback: (None, std::ptr::null_mut()),
+            notification_callback: (None, std::ptr::null_mut()),
+            state_callback: (None, std::ptr::null_mut()),
+        }
+    }
+
+    fn run(&mut self, timeout: u32) -> Result<(), RabcError> {
+        let result = self
+            .run_until(Instant::now() + Duration::from_secs(timeout.into()));
+        if let Err(e) = &result {
+            if e.kind().is_connection_error() {
+                self.change_state(RabcClientState::Disconnected, e);
+            }
+        }
+        result
+    }
+
+    fn run_until(&mut self, deadline: Instant) -> Result<(), RabcError> {
+        loop {
+            let remain = deadline.saturating_duration_since(Instant::now());
+            // Wait time of poll is in seconds
+            let wait_time =
+                remain.as_secs() as u32 + u32::from(remain.subsec_nanos() > 0);
+            for event in self.client.poll(wait_time)? {
+                self.dispatch(&event)?;
+            }
+            if Instant::now() >= deadline {
+                return Ok(());
+            }
+        }
+    }
+
+    fn dispatch(&mut self, event: &RabcEvent) -> Result<(), RabcError> {
+        match self.client.process_message(event)? {
+            Some(RabcClientMessage::Reply(reply)) => {
+                self.invoke_reply_callback(reply);
+            }
+            Some(RabcClientMessage::Notification(notification)) => {
+                self.invoke_notification_callback(&notification);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn invoke_reply_callback(&self, reply: RabcReply) {
+        let (Some(callback), userdata) = self.reply_callback else {
+            return;
+        };
+        let request = reply.request.as_deref().map(c_string);
+        let (reply, err) = match reply.result.and_then(reply_c_string) {
+            Ok(r) => (Some(r), None),
+            Err(e) => (None, Some(RabcCError::from(&e))),
+        };
+        callback(
+            request.as_ref().map_or(std::ptr::null(), |r| r.as_ptr()),
+            reply.as_ref().map_or(std::ptr::null(), |r| r.as_ptr()),
+            err.as_ref().map_or(std::ptr::null(), |e| e as *const _),
+            userdata,
+        );
+    }
+
+    fn invoke_notification_callback(&self, notification: &RabcNotification) {
+        let (Some(callback), userdata) = self.notification_callback else {
+            return;
+        };
+        match serde_json::to_string(notification) {
+            Ok(n) => callback(c_string(&n).as_ptr(), userdata),
+            Err(e) => {
+                log::error!("Failed to serialize {:?}: {}", notification, e)
+            }
+        }
+    }
+
+    fn change_state(&mut self, state: RabcClientState, err: &RabcError) {
+        if self.state == state {
+            return;
+        }
+        self.state = state;
+        if let (Some(callback), userdata) = self.state_callback {
+            let err = RabcCError::from(err);
+            callback(state as u32, &err, userdata);
+        }
+    }
+}
+
+/// Invoke `callback` with `userdata` for replies processed by
+/// rabc_client_run(), including the replies of heartbeat `ping` sent by the
+/// library. NULL `callback` stops it. The callback should not free the
+/// client.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_set_reply_callback(
+    client: *mut RabcCClient,
+    callback: RabcReplyCallback,
+    userdata: *mut c_void,
+) -> u32 {
+    catch_panic(RABC_FAIL, || match unsafe { client.as_mut() } {
+        Some(client) => {
+            client.reply_callback = (callback, userdata);
+            RABC_PASS
+        }
+        None => RABC_FAIL_NULL_POINTER,
+    })
+}
+
+/// Invoke `callback` with `userdata` for notifications processed by
+/// rabc_client_run(). NULL `callback` stops it. The callback should not
+/// free the client.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_set_notification_callback(
+    client: *mut RabcCClient,
+    callback: RabcNotificationCallback,
+    userdata: *mut c_void,
+) -> u32 {
+    catch_panic(RABC_FAIL, || match unsafe { client.as_mut() } {
+        Some(client) => {
+            client.notification_callback = (callback, userdata);
+            RABC_PASS
+        }
+        None => RABC_FAIL_NULL_POINTER,
+    })
+}
+
+/// Invoke `callback` with `userdata` when rabc_client_run() found the
+/// connection state changed. NULL `callback` stops it. The callback should
+/// not free the client.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_set_state_callback(
+    client: *mut RabcCClient,
+    callback: RabcStateCallback,
+    userdata: *mut c_void,
+) -> u32 {
+    catch_panic(RABC_FAIL, || match unsafe { client.as_mut() } {
+        Some(client) => {
+            client.state_callback = (callback, userdata);
+            RABC_PASS
+        }
+        None => RABC_FAIL_NULL_POINTER,
+    })
+}
+
+/// Send `request` to daemon, its reply is passed to the callback set by
+/// rabc_client_set_reply_callback() during rabc_client_run(), e.g.
+/// "subscribe" for notifications.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_send(
+    client: *mut RabcCClient,
+    request: *const c_char,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null() || request.is_null() || err.is_null() {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut *client };
+    let request = unsafe { CStr::from_ptr(request) };
+    call_with_error(err, || match request.to_str() {
+        Ok(request) => client.client.send(request),
+        Err(e) => Err(RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Request is not valid UTF-8: {}", e),
+        )),
+    })
+}
+
+/// Process events for `timeout` seconds and invoke the callbacks set by
+/// rabc_client_set_reply_callback(),
+/// rabc_client_set_notification_callback() and
+/// rabc_client_set_state_callback(). Error replied by daemon is passed to
+/// reply callback, other errors stop the run and are stored into `err`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_run(
+    client: *mut RabcCClient,
+    timeout: u32,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null() || err.is_null() {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut *client };
+    call_with_error(err, || client.run(timeout))
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use once_cell::sync::OnceCell;
+use rabc::{ErrorKind, RabcError};
+
+use crate::{c_string, catch_panic};
+
+// Symbol versions of rabc.map generated by build.rs
+#[cfg(not(test))]
+std::arch::global_asm!(include_str!(concat!(
+    env!("OUT_DIR"),
+    "/symver_error.s"
+)));
+
+const UNKNOWN_ERROR_KIND: &[u8] = b"Unknown\0";
+
+// Names of `enum rabc_error_kind` indexed by its value
+static KIND_NAMES: OnceCell<Vec<CString>> = OnceCell::new();
+
+/// Kind of error set by functions returning RABC_FAIL, use
+/// rabc_error_kind_to_str() to get its name.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RabcErrorKind {
+    IpcConnectionError = 0,
+    ExceededIpcMaxSize = 1,
+    InvalidArgument = 2,
+    Bug = 3,
+    Throttled = 4,
+    PermissionDenied = 5,
+    Timeout = 6,
+    NotConnected = 7,
+    ProtocolError = 8,
+    PeerClosed = 9,
+}
+
+impl From<ErrorKind> for RabcErrorKind {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::IpcConnectionError => Self::IpcConnectionError,
+            ErrorKind::ExceededIpcMaxSize => Self::ExceededIpcMaxSize,
+            ErrorKind::InvalidArgument => Self::InvalidArgument,
+            ErrorKind::Bug => Self::Bug,
+            ErrorKind::Throttled => Self::Throttled,
+            ErrorKind::PermissionDenied => Self::PermissionDenied,
+            ErrorKind::Timeout => Self::Timeout,
+            ErrorKind::NotConnected => Self::NotConnected,
+            ErrorKind::ProtocolError => Self::ProtocolError,
+            ErrorKind::PeerClosed => Self::PeerClosed,
+            _ => Self::Bug,
+        }
+    }
+}
+
+/// Value of `enum rabc_error_kind`.
+pub(crate) fn error_kind_to_u32(kind: ErrorKind) -> u32 {
+    RabcErrorKind::from(kind) as u32
+}
+
+/// Name of `enum rabc_error_kind`, e.g. "InvalidArgument". The returned
+/// string is static and should not be freed. "Unknown" is returned for
+/// invalid value.
+#[no_mangle]
+pub extern "C" fn rabc_error_kind_to_str(kind: u32) -> *const c_char {
+    let unknown = UNKNOWN_ERROR_KIND.as_ptr() as *const c_char;
+    catch_panic(unknown, || {
+        let names = KIND_NAMES.get_or_init(|| {
+            let mut kinds = ErrorKind::ALL.to_vec();
+            kinds.sort_by_key(|k| error_kind_to_u32(*k));
+            kinds
+                .iter()
+                .map(|k| CString::new(k.to_string()).unwrap_or_default())
+                .collect()
+        });
+        match names.get(kind as usize) {
+            Some(name) => name.as_ptr(),
+            None => unknown,
+        }
+    })
+}
+
+/// Error of the functions ending with `_with_error`, should be freed by
+/// rabc_error_free().
+#[derive(Debug)]
+pub struct RabcCError {
+    kind: u32,
+    msg: CString,
+    errno: i32,
+}
+
+impl From<&RabcError> for RabcCError {
+    fn from(e: &RabcError) -> Self {
+        Self {
+            kind: error_kind_to_u32(e.kind()),
+            msg: c_string(&e.full_msg()),
+            errno: e.errno().unwrap_or_default(),
+        }
+    }
+}
+
+/// `enum rabc_error_kind` of the error
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_error_kind(err: *const RabcCError) -> u32 {
+    let bug = RabcErrorKind::Bug as u32;
+    catch_panic(bug, || match unsafe { err.as_ref() } {
+        Some(e) => e.kind,
+        None => bug,
+    })
+}
+
+/// Error message prefixed by context, valid until rabc_error_free()
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_error_msg(err: *const RabcCError) -> *const c_char {
+    catch_panic(std::ptr::null(), || match unsafe { err.as_ref() } {
+        Some(e) => e.msg.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// OS error number causing the error, 0 if not caused by system call
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_error_errno(err: *const RabcCError) -> i32 {
+    catch_panic(0, || match unsafe { err.as_ref() } {
+        Some(e) => e.errno,
+        None => 0,
+    })
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_error_free(err: *mut RabcCError) {
+    if !err.is_null() {
+        catch_panic((), || unsafe {
+            drop(Box::from_raw(err));
+        })
+    }
+}
@@ -1,26 +1,49 @@
 // SPDX-License-Identifier: Apache-2.0
 
+mod client;
+mod error;
 mod logger;
+mod unit_tests;
 
 use std::ffi::CString;
-use std::os::raw::c_char;
-use std::time::SystemTime;
+use std::os::raw::{c_char, c_void};
+use std::panic::AssertUnwindSafe;
 
 use once_cell::sync::OnceCell;
 use rabc::{ErrorKind, RabcClient, RabcError, RabcEvent};
 
-use crate::logger::MemoryLogger;
+use crate::client::RabcCClient;
+use crate::error::RabcCError;
+use crate::logger::{LogCapture, MemoryLogger, RabcLogCallback, RabcLogLevel};
 
-const RABC_PASS: u32 = 0;
-const RABC_FAIL: u32 = 1;
-const RABC_FAIL_NULL_POINTER: u32 = 2;
+// Symbol versions of rabc.map generated by build.rs
+#[cfg(not(test))]
+std::arch::global_asm!(include_str!(concat!(env!("OUT_DIR"), "/symver_lib.s")));
 
-static INSTANCE: OnceCell<MemoryLogger> = OnceCell::new();
+pub const RABC_PASS: u32 = 0;
+pub const RABC_FAIL: u32 = 1;
+pub const RABC_FAIL_NULL_POINTER: u32 = 2;
 
+static LOGGER: MemoryLogger = MemoryLogger;
+static LOGGER_INIT: OnceCell<()> = OnceCell::new();
+
+/// The `log` output argument of below functions is a JSON array of log
+/// records, e.g.
+///   [{"time": "2022-07-01T08:00:00.000123Z", "level": "DEBUG",
+///     "target": "rabc::client", "msg": "...", "module": "rabc::client",
+///     "file": "src/lib/client.rs", "line": 10, "thread": "main",
+///     "fields": {"key": "value"}}]
+/// The `module`, `file`, `line`, `thread` and `fields` are optional.
+/// The `err_kind` and `err_msg` output arguments are only set when
+/// RABC_FAIL is returned, `err_kind` holds the name of error kind, e.g.
+/// "InvalidArgument". All the output strings should be freed by
+/// rabc_cstring_free(). Each of them has a `_with_error` variant taking
+/// `struct rabc_error` instead, e.g. rabc_client_new_with_error() for
+/// rabc_client_new(), which provides `enum rabc_error_kind`.
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn rabc_client_new(
-    client: *mut *mut RabcClient,
+    client: *mut *mut RabcCClient,
     log: *mut *mut c_char,
     err_kind: *mut *mut c_char,
     err_msg: *mut *mut c_char,
@@ -32,52 +55,39 @@ pub extern "C" fn rabc_client_new(
     {
         return RABC_FAIL_NULL_POINTER;
     }
+    call_with_log(log, err_kind, err_msg, || client_new(client))
+}
 
-    unsafe {
-        *client = std::ptr::null_mut();
-        *log = std::ptr::null_mut();
-        *err_kind = std::ptr::null_mut();
-        *err_msg = std::ptr::null_mut();
+/// Same as rabc_client_new() but store the error into `err` which is set
+/// to NULL on success. Logs are only available through
+/// rabc_set_log_callback().
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_new_with_error(
+    client: *mut *mut RabcCClient,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null() || err.is_null() {
+        return RABC_FAIL_NULL_POINTER;
     }
+    call_with_error(err, || client_new(client))
+}
 
-    let logger = match init_logger() {
-        Ok(l) => l,
-        Err(e) => {
-            unsafe {
-                *err_msg =
-                    CString::new(format!("Failed to setup logger: {}", e))
-                        .unwrap()
-                        .into_raw();
-            }
-            return RABC_FAIL;
-        }
-    };
-    let now = SystemTime::now();
-
-    let result = RabcClient::new();
-
+fn client_new(client: *mut *mut RabcCClient) -> Result<(), RabcError> {
     unsafe {
-        *log = CString::new(logger.drain(now)).unwrap().into_raw();
+        *client = std::ptr::null_mut();
     }
-
-    match result {
-        Ok(c) => unsafe {
-            *client = Box::into_raw(Box::new(c));
-            RABC_PASS
-        },
-        Err(e) => unsafe {
-            *err_msg = CString::new(e.msg()).unwrap().into_raw();
-            *err_kind =
-                CString::new(format!("{}", &e.kind())).unwrap().into_raw();
-            RABC_FAIL
-        },
+    let c = RabcCClient::new(RabcClient::new()?);
+    unsafe {
+        *client = Box::into_raw(Box::new(c));
     }
+    Ok(())
 }
 
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn rabc_client_poll(
-    client: *mut RabcClient,
+    client: *mut RabcCClient,
     wait_time: u32,
     events: *mut *mut u64,
     event_count: *mut u64,
@@ -94,205 +104,446 @@ pub extern "C" fn rabc_client_poll(
     {
         return RABC_FAIL_NULL_POINTER;
     }
+    let client = unsafe { &mut (*client).client };
+    call_with_log(log, err_kind, err_msg, || {
+        client_poll(client, wait_time, events, event_count)
+    })
+}
+
+/// Same as rabc_client_poll() but store the error into `err`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_poll_with_error(
+    client: *mut RabcCClient,
+    wait_time: u32,
+    events: *mut *mut u64,
+    event_count: *mut u64,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null()
+        || events.is_null()
+        || event_count.is_null()
+        || err.is_null()
+    {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut (*client).client };
+    call_with_error(err, || client_poll(client, wait_time, events, event_count))
+}
 
+fn client_poll(
+    client: &mut RabcClient,
+    wait_time: u32,
+    events: *mut *mut u64,
+    event_count: *mut u64,
+) -> Result<(), RabcError> {
     unsafe {
         *event_count = 0;
         *events = std::ptr::null_mut();
-        *log = std::ptr::null_mut();
-        *err_kind = std::ptr::null_mut();
-        *err_msg = std::ptr::null_mut();
     }
-
-    let client: &mut RabcClient = unsafe { &mut *client };
-
-    let logger = match init_logger() {
-        Ok(l) => l,
-        Err(e) => {
-            unsafe {
-                *err_msg =
-                    CString::new(format!("Failed to setup logger: {}", e))
-                        .unwrap()
-                        .into_raw();
-            }
-            return RABC_FAIL;
+    let result_events = client.poll(wait_time)?;
+    if !result_events.is_empty() {
+        let result_events: Vec<u64> =
+            result_events.as_slice().iter().map(|e| *e as u64).collect();
+        let event_ids_len = result_events.len() as u64;
+        // We trust C library user to use `rabc_events_free()`
+        let mut event_ids_box = result_events.into_boxed_slice();
+        unsafe {
+            *event_count = event_ids_len;
+            *events = event_ids_box.as_mut_ptr();
         }
-    };
-    let now = SystemTime::now();
+        std::mem::forget(event_ids_box);
+    }
+    Ok(())
+}
 
-    let result = client.poll(wait_time);
+/// Reply holding NUL byte fails with ProtocolError, use
+/// rabc_client_process_bytes() for it.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_process(
+    client: *mut RabcCClient,
+    event: u64,
+    reply: *mut *mut c_char,
+    log: *mut *mut c_char,
+    err_kind: *mut *mut c_char,
+    err_msg: *mut *mut c_char,
+) -> u32 {
+    if client.is_null()
+        || reply.is_null()
+        || log.is_null()
+        || err_kind.is_null()
+        || err_msg.is_null()
+    {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut (*client).client };
+    call_with_log(log, err_kind, err_msg, || {
+        client_process(client, event, reply)
+    })
+}
 
-    unsafe {
-        *log = CString::new(logger.drain(now)).unwrap().into_raw();
+/// Same as rabc_client_process() but store the error into `err`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_process_with_error(
+    client: *mut RabcCClient,
+    event: u64,
+    reply: *mut *mut c_char,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null() || reply.is_null() || err.is_null() {
+        return RABC_FAIL_NULL_POINTER;
     }
+    let client = unsafe { &mut (*client).client };
+    call_with_error(err, || client_process(client, event, reply))
+}
 
-    match result {
-        Ok(result_events) => {
-            if !result_events.is_empty() {
-                let result_events: Vec<u64> = result_events
-                    .as_slice()
-                    .iter()
-                    .map(|e| *e as u64)
-                    .collect();
-                let event_ids_len = result_events.len() as u64;
-                // We trust C library user to use `rabc_events_free()`
-                let mut event_ids_box = result_events.into_boxed_slice();
-                unsafe {
-                    *event_count = event_ids_len;
-                    *events = event_ids_box.as_mut_ptr();
-                }
-                std::mem::forget(event_ids_box);
+fn client_process(
+    client: &mut RabcClient,
+    event: u64,
+    reply: *mut *mut c_char,
+) -> Result<(), RabcError> {
+    unsafe {
+        *reply = std::ptr::null_mut();
+    }
+    let event = RabcEvent::try_from(event)?;
+    if let Some(r) = client.process_bytes(&event)? {
+        if !r.is_empty() {
+            let r = reply_c_string(r)?;
+            unsafe {
+                *reply = r.into_raw();
             }
-            RABC_PASS
         }
-        Err(e) => unsafe {
-            *err_msg = CString::new(e.msg()).unwrap().into_raw();
-            *err_kind =
-                CString::new(format!("{}", &e.kind())).unwrap().into_raw();
-            RABC_FAIL
-        },
     }
+    Ok(())
 }
 
+/// Same as rabc_client_process() but store the reply as bytes into `reply`
+/// with its length stored into `reply_len`, NUL bytes of the reply are
+/// kept. The `reply` should be freed by rabc_bytes_free().
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn rabc_client_process(
-    client: *mut RabcClient,
+pub extern "C" fn rabc_client_process_bytes(
+    client: *mut RabcCClient,
     event: u64,
-    reply: *mut *mut c_char,
+    reply: *mut *mut u8,
+    reply_len: *mut u64,
     log: *mut *mut c_char,
     err_kind: *mut *mut c_char,
     err_msg: *mut *mut c_char,
 ) -> u32 {
     if client.is_null()
         || reply.is_null()
+        || reply_len.is_null()
         || log.is_null()
         || err_kind.is_null()
         || err_msg.is_null()
     {
         return RABC_FAIL_NULL_POINTER;
     }
+    let client = unsafe { &mut (*client).client };
+    call_with_log(log, err_kind, err_msg, || {
+        client_process_bytes(client, event, reply, reply_len)
+    })
+}
+
+/// Same as rabc_client_process_bytes() but store the error into `err`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_process_bytes_with_error(
+    client: *mut RabcCClient,
+    event: u64,
+    reply: *mut *mut u8,
+    reply_len: *mut u64,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null()
+        || reply.is_null()
+        || reply_len.is_null()
+        || err.is_null()
+    {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut (*client).client };
+    call_with_error(err, || {
+        client_process_bytes(client, event, reply, reply_len)
+    })
+}
 
+fn client_process_bytes(
+    client: &mut RabcClient,
+    event: u64,
+    reply: *mut *mut u8,
+    reply_len: *mut u64,
+) -> Result<(), RabcError> {
     unsafe {
         *reply = std::ptr::null_mut();
-        *log = std::ptr::null_mut();
-        *err_kind = std::ptr::null_mut();
-        *err_msg = std::ptr::null_mut();
+        *reply_len = 0;
     }
-
-    let client: &mut RabcClient = unsafe { &mut *client };
-
-    let logger = match init_logger() {
-        Ok(l) => l,
-        Err(e) => {
+    let event = RabcEvent::try_from(event)?;
+    if let Some(r) = client.process_bytes(&event)? {
+        if !r.is_empty() {
+            let data = r.into_boxed_slice();
             unsafe {
-                *err_msg =
-                    CString::new(format!("Failed to setup logger: {}", e))
-                        .unwrap()
-                        .into_raw();
+                *reply_len = data.len() as u64;
+                // We trust C library user to use `rabc_bytes_free()`
+                *reply = Box::into_raw(data) as *mut u8;
             }
-            return RABC_FAIL;
         }
-    };
-    let now = SystemTime::now();
+    }
+    Ok(())
+}
 
-    let event = match RabcEvent::try_from(event) {
-        Ok(e) => e,
-        Err(e) => {
-            unsafe {
-                *err_msg = CString::new(e.msg()).unwrap().into_raw();
-                *err_kind =
-                    CString::new(format!("{}", &e.kind())).unwrap().into_raw();
-            }
-            return RABC_FAIL;
-        }
-    };
+/// Ask daemon to stream its logs up to specified `enum rabc_log_level`,
+/// the records are included in the log of later rabc_client_process()
+/// calls. RABC_LOG_LEVEL_OFF stops the streaming.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_request_daemon_logs(
+    client: *mut RabcCClient,
+    level: u32,
+    log: *mut *mut c_char,
+    err_kind: *mut *mut c_char,
+    err_msg: *mut *mut c_char,
+) -> u32 {
+    if client.is_null()
+        || log.is_null()
+        || err_kind.is_null()
+        || err_msg.is_null()
+    {
+        return RABC_FAIL_NULL_POINTER;
+    }
+    let client = unsafe { &mut (*client).client };
+    call_with_log(log, err_kind, err_msg, || {
+        client.request_daemon_logs(log_level_from_u32(level)?)
+    })
+}
 
-    let result = client.process(&event);
-    unsafe {
-        *log = CString::new(logger.drain(now)).unwrap().into_raw();
+/// Same as rabc_client_request_daemon_logs() but store the error into
+/// `err`.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_client_request_daemon_logs_with_error(
+    client: *mut RabcCClient,
+    level: u32,
+    err: *mut *mut RabcCError,
+) -> u32 {
+    if client.is_null() || err.is_null() {
+        return RABC_FAIL_NULL_POINTER;
     }
+    let client = unsafe { &mut (*client).client };
+    call_with_error(err, || {
+        client.request_daemon_logs(log_level_from_u32(level)?)
+    })
+}
 
-    match result {
-        Ok(Some(r)) => {
-            if !r.is_empty() {
-                unsafe {
-                    *reply = CString::new(r).unwrap().into_raw();
-                }
-            }
-            RABC_PASS
+/// Set max `enum rabc_log_level` of logs, default to
+/// RABC_LOG_LEVEL_DEBUG. The level requested by daemon via the
+/// `client_log_level` request takes precedence.
+#[no_mangle]
+pub extern "C" fn rabc_set_log_level(level: u32) -> u32 {
+    catch_panic(RABC_FAIL, || {
+        let level = match log_level_from_u32(level) {
+            Ok(l) => l,
+            Err(_) => return RABC_FAIL,
+        };
+        if install_logger().is_err() {
+            return RABC_FAIL;
         }
-        Ok(None) => RABC_PASS,
-        Err(e) => unsafe {
-            *err_msg = CString::new(e.msg()).unwrap().into_raw();
-            *err_kind =
-                CString::new(format!("{}", &e.kind())).unwrap().into_raw();
-            RABC_FAIL
-        },
-    }
+        logger::set_log_level(level);
+        RABC_PASS
+    })
+}
+
+/// Send logs to `callback` with `userdata` instead of the `log` output
+/// argument of other functions, which will be set to NULL. The callback
+/// might be invoked from any thread using the library. NULL `callback`
+/// restores the `log` output argument.
+#[no_mangle]
+pub extern "C" fn rabc_set_log_callback(
+    callback: RabcLogCallback,
+    userdata: *mut c_void,
+) -> u32 {
+    catch_panic(RABC_FAIL, || {
+        if install_logger().is_err() {
+            return RABC_FAIL;
+        }
+        logger::set_log_callback(callback, userdata);
+        RABC_PASS
+    })
+}
+
+// Convert `enum rabc_log_level` to log::LevelFilter
+fn log_level_from_u32(level: u32) -> Result<log::LevelFilter, RabcError> {
+    RabcLogLevel::try_from(level).map(log::LevelFilter::from)
 }
 
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
-pub extern "C" fn rabc_client_free(client: *mut RabcClient) {
+pub extern "C" fn rabc_client_free(client: *mut RabcCClient) {
     if !client.is_null() {
-        unsafe {
+        catch_panic((), || unsafe {
             drop(Box::from_raw(client));
-        }
+        })
     }
 }
 
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn rabc_cstring_free(cstring: *mut c_char) {
-    unsafe {
-        if !cstring.is_null() {
+    if !cstring.is_null() {
+        catch_panic((), || unsafe {
             drop(CString::from_raw(cstring));
-        }
+        })
     }
 }
 
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 #[no_mangle]
 pub extern "C" fn rabc_events_free(events: *mut u64, event_count: u64) {
-    unsafe {
-        if !events.is_null() {
+    if !events.is_null() {
+        catch_panic((), || unsafe {
             let events_slice =
                 std::slice::from_raw_parts_mut(events, event_count as usize);
             drop(Box::from_raw(events_slice));
-        }
+        })
+    }
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn rabc_bytes_free(data: *mut u8, len: u64) {
+    if !data.is_null() {
+        catch_panic((), || unsafe {
+            drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                data,
+                len as usize,
+            )));
+        })
     }
 }
 
-fn init_logger() -> Result<&'static MemoryLogger, RabcError> {
-    match INSTANCE.get() {
-        Some(l) => {
-            l.add_consumer();
-            Ok(l)
+// Invoke `f` with its logs stored into `log` and error stored into
+// `err_kind` and `err_msg`.
+fn call_with_log<F>(
+    log: *mut *mut c_char,
+    err_kind: *mut *mut c_char,
+    err_msg: *mut *mut c_char,
+    f: F,
+) -> u32
+where
+    F: FnOnce() -> Result<(), RabcError>,
+{
+    unsafe {
+        *log = std::ptr::null_mut();
+        *err_kind = std::ptr::null_mut();
+        *err_msg = std::ptr::null_mut();
+    }
+    let result = catch_panic_as_error(|| {
+        install_logger()?;
+        let capture = LogCapture::start();
+        let result = f();
+        // Log is sent to callback instead if set
+        if let Some(logs) = capture.finish() {
+            unsafe {
+                *log = c_string(&logs).into_raw();
+            }
         }
-        None => {
-            if INSTANCE.set(MemoryLogger::new()).is_err() {
-                return Err(RabcError::new(
-                    ErrorKind::Bug,
-                    "Failed to set once_sync for logger".to_string(),
-                ));
+        result
+    });
+    match result {
+        Ok(()) => RABC_PASS,
+        Err(e) => {
+            unsafe {
+                *err_msg = c_string(&e.full_msg()).into_raw();
+                *err_kind = c_string(&e.kind().to_string()).into_raw();
             }
-            if let Some(l) = INSTANCE.get() {
-                if let Err(e) = log::set_logger(l) {
-                    Err(RabcError::new(
-                        ErrorKind::Bug,
-                        format!("Failed to log::set_logger: {}", e),
-                    ))
-                } else {
-                    l.add_consumer();
-                    log::set_max_level(log::LevelFilter::Debug);
-                    Ok(l)
-                }
-            } else {
-                Err(RabcError::new(
-                    ErrorKind::Bug,
-                    "Failed to get logger from once_sync".to_string(),
-                ))
+            RABC_FAIL
+        }
+    }
+}
+
+// Invoke `f` with error stored into `err`, logs are only available through
+// rabc_set_log_callback().
+pub(crate) fn call_with_error<F>(err: *mut *mut RabcCError, f: F) -> u32
+where
+    F: FnOnce() -> Result<(), RabcError>,
+{
+    unsafe {
+        *err = std::ptr::null_mut();
+    }
+    match catch_panic_as_error(|| install_logger().and_then(|_| f())) {
+        Ok(()) => RABC_PASS,
+        Err(e) => {
+            unsafe {
+                *err = Box::into_raw(Box::new(RabcCError::from(&e)));
             }
+            RABC_FAIL
         }
     }
 }
+
+// Unwinding into C is undefined behavior, hence convert panic of `f` to
+// error of `ErrorKind::Bug`.
+fn catch_panic_as_error<F>(f: F) -> Result<(), RabcError>
+where
+    F: FnOnce() -> Result<(), RabcError>,
+{
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let msg = panic
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown reason");
+        Err(RabcError::new(
+            ErrorKind::Bug,
+            format!("Unexpected panic in librabc: {}", msg),
+        ))
+    })
+}
+
+/// Return `on_panic` if `f` panicked, used by functions without error
+/// output.
+pub(crate) fn catch_panic<T, F>(on_panic: T, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    std::panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+// Install the logger once
+fn install_logger() -> Result<(), RabcError> {
+    LOGGER_INIT
+        .get_or_try_init(|| {
+            log::set_logger(&LOGGER).map_err(|e| {
+                RabcError::new(
+                    ErrorKind::Bug,
+                    format!("Failed to log::set_logger: {}", e),
+                )
+            })?;
+            // Filtered by MemoryLogger which only handles `rabc::` targets
+            log::set_max_level(log::LevelFilter::Trace);
+            Ok(())
+        })
+        .copied()
+}
+
+/// C string cannot hold NUL byte, escape it as `\0` in log and error text.
+pub(crate) fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "\\0")).unwrap_or_default()
+}
+
+/// Reply is never altered, fail if it holds NUL byte.
+pub(crate) fn reply_c_string(reply: Vec<u8>) -> Result<CString, RabcError> {
+    CString::new(reply).map_err(|e| {
+        RabcError::new(
+            ErrorKind::ProtocolError,
+            format!(
+                "Reply holds NUL byte at position {}, use \
+                 rabc_client_process_bytes() instead",
+                e.nul_position()
+            ),
+        )
+    })
+}
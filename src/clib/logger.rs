@@ -1,128 +1,217 @@
 // This is based on the work of https://github.com/gahag/memory_logger
 // which is MIT licensed.
 
-use std::sync::{
-    atomic::{AtomicU16, Ordering},
-    Mutex,
-};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
 use std::time::SystemTime;
 
-use serde::ser::{Serialize, SerializeMap, Serializer};
+use rabc::{ErrorKind, RabcError, RabcLogRecord};
 
-const INITIAL_VEC_CAPACITY: usize = 256;
+use crate::c_string;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub(crate) struct LogEntry {
-    time: SystemTime,
-    level: log::Level,
-    file: String,
-    msg: String,
+const INITIAL_VEC_CAPACITY: usize = 16;
+
+/// Log record of specified `enum rabc_log_level` passed to the callback
+/// set by rabc_set_log_callback(), the strings are only valid during the
+/// callback.
+pub type RabcLogCallback = Option<
+    extern "C" fn(
+        level: u32,
+        target: *const c_char,
+        file: *const c_char,
+        line: u32,
+        msg: *const c_char,
+        userdata: *mut c_void,
+    ),
+>;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RabcLogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
 }
 
-impl Default for LogEntry {
-    fn default() -> Self {
-        Self {
-            time: SystemTime::UNIX_EPOCH,
-            level: log::Level::Debug,
-            file: String::new(),
-            msg: String::new(),
+impl TryFrom<u32> for RabcLogLevel {
+    type Error = RabcError;
+    fn try_from(v: u32) -> Result<Self, RabcError> {
+        match v {
+            x if x == RabcLogLevel::Off as u32 => Ok(RabcLogLevel::Off),
+            x if x == RabcLogLevel::Error as u32 => Ok(RabcLogLevel::Error),
+            x if x == RabcLogLevel::Warn as u32 => Ok(RabcLogLevel::Warn),
+            x if x == RabcLogLevel::Info as u32 => Ok(RabcLogLevel::Info),
+            x if x == RabcLogLevel::Debug as u32 => Ok(RabcLogLevel::Debug),
+            x if x == RabcLogLevel::Trace as u32 => Ok(RabcLogLevel::Trace),
+            _ => Err(RabcError::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid log level {}", v),
+            )),
         }
     }
 }
 
-impl Serialize for LogEntry {
-    // Serialize is also used for verification.
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut map = serializer.serialize_map(Some(4))?;
-
-        map.serialize_entry(
-            "time",
-            &format!(
-                "{}",
-                self.time
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs()
-            ),
-        )?;
-        map.serialize_entry("level", self.level.as_str())?;
-        map.serialize_entry("file", self.file.as_str())?;
-        map.serialize_entry("msg", self.msg.as_str())?;
-        map.end()
+impl From<RabcLogLevel> for log::LevelFilter {
+    fn from(level: RabcLogLevel) -> Self {
+        match level {
+            RabcLogLevel::Off => Self::Off,
+            RabcLogLevel::Error => Self::Error,
+            RabcLogLevel::Warn => Self::Warn,
+            RabcLogLevel::Info => Self::Info,
+            RabcLogLevel::Debug => Self::Debug,
+            RabcLogLevel::Trace => Self::Trace,
+        }
     }
 }
 
-impl From<&log::Record<'_>> for LogEntry {
-    fn from(r: &log::Record<'_>) -> Self {
-        Self {
-            time: SystemTime::now(),
-            level: r.level(),
-            file: format!(
-                "{}:{}:{}",
-                r.module_path().unwrap_or_default(),
-                r.file().unwrap_or_default(),
-                r.line().unwrap_or_default()
-            ),
-            msg: r.args().to_string(),
-        }
-    }
+// Max level set by rabc_set_log_level()
+static LOG_LEVEL: AtomicUsize =
+    AtomicUsize::new(log::LevelFilter::Debug as usize);
+
+pub(crate) fn set_log_level(level: log::LevelFilter) {
+    LOG_LEVEL.store(level as usize, Ordering::Relaxed);
 }
 
-/// A blocking memory logger. Logging and read operations may block.
-///
-/// You should have only a single instance of this in your program.
-#[derive(Default, Debug)]
-pub(crate) struct MemoryLogger {
-    consumer_count: AtomicU16,
-    logs: Mutex<Vec<LogEntry>>,
+// Callback and its userdata stored as address, as raw pointer is not Sync
+static CALLBACK: RwLock<(RabcLogCallback, usize)> = RwLock::new((None, 0));
+
+/// Send records to specified callback instead of [LogCapture], `None` to
+/// restore capturing.
+pub(crate) fn set_log_callback(
+    callback: RabcLogCallback,
+    userdata: *mut c_void,
+) {
+    *CALLBACK.write().expect("inner lock poisoned") =
+        (callback, userdata as usize);
 }
 
+thread_local! {
+    // Stack of active captures of current thread, records are stored into
+    // the innermost one.
+    static CAPTURES: RefCell<Vec<Vec<RabcLogRecord>>> = const {
+        RefCell::new(Vec::new())
+    };
+}
+
+/// Logger invoking the log callback if set, otherwise storing records into
+/// the [LogCapture] of the logging thread. Records logged without active
+/// capture are discarded. The level requested by daemon takes precedence
+/// over the one set by rabc_set_log_level().
+#[derive(Default, Debug)]
+pub(crate) struct MemoryLogger;
+
 impl log::Log for MemoryLogger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.target().starts_with("rabc::")
+        let level = rabc::daemon_requested_log_level().unwrap_or_else(|| {
+            log::LevelFilter::iter()
+                .nth(LOG_LEVEL.load(Ordering::Relaxed))
+                .unwrap_or(log::LevelFilter::Debug)
+        });
+        metadata.target().starts_with("rabc::") && metadata.level() <= level
     }
 
     fn log(&self, record: &log::Record) {
-        if self.enabled(record.metadata()) {
-            let mut logs = self.logs.lock().expect("inner lock poisoned");
-            logs.push(LogEntry::from(record))
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let (callback, userdata) =
+            *CALLBACK.read().expect("inner lock poisoned");
+        if callback.is_some() {
+            invoke_callback(callback, userdata as *mut c_void, record);
+        } else {
+            CAPTURES.with(|captures| {
+                // Logging while serializing captured logs is discarded
+                if let Ok(mut captures) = captures.try_borrow_mut() {
+                    if let Some(capture) = captures.last_mut() {
+                        capture.push(RabcLogRecord::from_record(
+                            record,
+                            SystemTime::now(),
+                        ));
+                    }
+                }
+            });
         }
     }
 
     fn flush(&self) {}
 }
 
-impl MemoryLogger {
-    pub(crate) fn new() -> Self {
+/// Invoke `f` with the captures of current thread borrowed, hence
+/// [LogCapture::start()] panics within it.
+#[cfg(test)]
+pub(crate) fn with_captures_borrowed<T>(f: impl FnOnce() -> T) -> T {
+    CAPTURES.with(|captures| {
+        let _captures = captures.borrow();
+        f()
+    })
+}
+
+/// Collect the records logged by current thread until
+/// [LogCapture::finish()] or drop, so each C API call returns exactly its
+/// own logs regardless of other threads.
+#[derive(Debug)]
+pub(crate) struct LogCapture {
+    // Bound to the thread started the capture
+    _not_send: PhantomData<*const ()>,
+}
+
+impl LogCapture {
+    pub(crate) fn start() -> Self {
+        CAPTURES.with(|captures| {
+            captures
+                .borrow_mut()
+                .push(Vec::with_capacity(INITIAL_VEC_CAPACITY))
+        });
         Self {
-            consumer_count: AtomicU16::new(0),
-            logs: Mutex::new(Vec::with_capacity(INITIAL_VEC_CAPACITY)),
+            _not_send: PhantomData,
         }
     }
 
-    pub(crate) fn add_consumer(&self) {
-        self.consumer_count.fetch_add(1, Ordering::SeqCst);
+    /// Stop capturing and return the captured records in JSON, `None` if
+    /// records are sent to log callback instead.
+    pub(crate) fn finish(self) -> Option<String> {
+        if CALLBACK.read().expect("inner lock poisoned").0.is_some() {
+            return None;
+        }
+        let logs = CAPTURES.with(|captures| {
+            captures.borrow_mut().last_mut().map(std::mem::take)
+        });
+        Some(
+            serde_json::to_string(&logs.unwrap_or_default())
+                .unwrap_or_default(),
+        )
     }
+}
 
-    /// Drain the whole buffered log if this is the last consumer, only return
-    /// logs since specified time.
-    /// Note that this locks the logger, causing logging to block.
-    pub(crate) fn drain(&self, since: SystemTime) -> String {
-        let mut logs = self.logs.lock().expect("inner lock poisoned");
-        let ret = serde_json::to_string(
-            &logs
-                .as_slice()
-                .iter()
-                .filter(|l| l.time >= since)
-                .collect::<Vec<&LogEntry>>(),
-        )
-        .unwrap_or_default();
-        if self.consumer_count.fetch_sub(1, Ordering::SeqCst) == 1 {
-            logs.clear();
-        }
-        ret
+impl Drop for LogCapture {
+    fn drop(&mut self) {
+        CAPTURES.with(|captures| captures.borrow_mut().pop());
     }
 }
+
+fn invoke_callback(
+    callback: RabcLogCallback,
+    userdata: *mut c_void,
+    record: &log::Record,
+) {
+    let Some(callback) = callback else {
+        return;
+    };
+    let target = c_string(record.target());
+    let file = c_string(record.file().unwrap_or_default());
+    let msg = c_string(&record.args().to_string());
+    callback(
+        record.level() as u32,
+        target.as_ptr(),
+        file.as_ptr(),
+        record.line().unwrap_or_default(),
+        msg.as_ptr(),
+        userdata,
+    );
+}
@@ -0,0 +1,30 @@
+#!/bin/bash
+# SPDX-License-Identifier: Apache-2.0
+#
+# Check the dynamic symbol table of built librabc.so against each
+# abi/rabc-<version>.symbols: every symbol of released version should still
+# be exported with the same type as the default `RABC_<version>` version.
+
+if [ -z "$1" ];then
+    echo "Usage: $0 <path_of_librabc.so>" >&2
+    exit 1
+fi
+
+CLIB_PATH=$(dirname $(dirname "$(realpath "$0")"))
+EXPORTED=$(nm -D --defined-only --with-symbol-versions "$1" | \
+    cut -d ' ' -f 2-) || exit 1
+
+rc=0
+for SYMBOLS_FILE in $CLIB_PATH/abi/rabc-*.symbols; do
+    VERSION=$(basename $SYMBOLS_FILE .symbols)
+    VERSION=${VERSION#rabc-}
+    while read TYPE SYMBOL; do
+        EXPECTED="$TYPE $SYMBOL@@RABC_$VERSION"
+        if ! grep -qxF "$EXPECTED" <<< "$EXPORTED"; then
+            echo "Symbol of $(basename $SYMBOLS_FILE) not exported by" \
+                "$1: $EXPECTED" >&2
+            rc=1
+        fi
+    done < $SYMBOLS_FILE
+done
+exit $rc
@@ -7,6 +7,34 @@
 #define WAIT_TIME               10
 #define PROCESS_LOOP_COUNT      10
 
+static void log_callback(uint32_t level, const char *target,
+                         const char *file, uint32_t line, const char *msg,
+                         void *userdata) {
+    uint64_t *count = userdata;
+
+    (*count)++;
+    printf("Log callback %u %s %s:%u %s\n", level, target, file, line, msg);
+}
+
+static void reply_callback(const char *request, const char *reply,
+                           const struct rabc_error *err, void *userdata) {
+    uint64_t *count = userdata;
+
+    (*count)++;
+    if (err != NULL)
+        printf("Reply callback %s: error %s\n", request, rabc_error_msg(err));
+    else
+        printf("Reply callback %s: %s\n", request, reply);
+}
+
+static void notification_callback(const char *notification,
+                                  void *userdata) {
+    uint64_t *count = userdata;
+
+    (*count)++;
+    printf("Notification callback %s\n", notification);
+}
+
 int process(struct rabc_client *client) {
     int rc = EXIT_SUCCESS;
     uint32_t ret = RABC_PASS;
@@ -21,7 +49,8 @@ int process(struct rabc_client *client) {
     ret = rabc_client_poll(client, WAIT_TIME,
                            &events, &event_count, &log, &err_kind,
                            &err_msg);
-    printf("Log %s\n", log);
+    if (log != NULL)
+        printf("Log %s\n", log);
     rabc_cstring_free(log);
 
     if (ret != RABC_PASS) {
@@ -35,7 +64,8 @@ int process(struct rabc_client *client) {
     for (i=0; i < event_count; ++i) {
         ret = rabc_client_process(client, events[i], &reply, &log, &err_kind,
                                   &err_msg);
-        printf("Log %s\n", log);
+        if (log != NULL)
+            printf("Log %s\n", log);
         rabc_cstring_free(log);
         if (ret != RABC_PASS) {
             printf("Error: %s: %s\n", err_kind, err_msg);
@@ -62,9 +92,17 @@ int main(void) {
     char *err_msg = NULL;
     char *log = NULL;
     int i = 0;
+    uint64_t log_count = 0;
+    struct rabc_client *client2 = NULL;
+    struct rabc_error *err = NULL;
+    char *reply = NULL;
+    uint64_t reply_count = 0;
+    struct rabc_client *client3 = NULL;
+    uint64_t notification_count = 0;
 
     ret = rabc_client_new(&client, &log, &err_kind, &err_msg);
-    printf("Log %s\n", log);
+    if (log != NULL)
+        printf("Log %s\n", log);
 
     if (ret != RABC_PASS) {
         printf("Error: %s: %s\n", err_kind, err_msg);
@@ -78,7 +116,83 @@ int main(void) {
         }
     }
 
+    if (rabc_set_log_level(RABC_LOG_LEVEL_TRACE) != RABC_PASS ||
+        rabc_set_log_level(RABC_LOG_LEVEL_TRACE + 1) != RABC_FAIL ||
+        rabc_set_log_callback(log_callback, &log_count) != RABC_PASS) {
+        printf("Error: failed to set log level or callback\n");
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+    /* Timer expires every 2 seconds, wait for it */
+    for (i = 0; i < PROCESS_LOOP_COUNT && log_count == 0; ++i) {
+        if (process(client) != EXIT_SUCCESS) {
+            rc = EXIT_FAILURE;
+            goto out;
+        }
+    }
+    rabc_set_log_callback(NULL, NULL);
+    if (log_count == 0) {
+        printf("Error: log callback not invoked\n");
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+
+    if (rabc_client_new_with_error(&client2, &err) != RABC_PASS) {
+        printf("Error: %s: %s\n",
+               rabc_error_kind_to_str(rabc_error_kind(err)),
+               rabc_error_msg(err));
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+    /* Invalid event ID should fail with error handle set */
+    if (rabc_client_process_with_error(client2, UINT64_MAX, &reply, &err) !=
+        RABC_FAIL || err == NULL || reply != NULL ||
+        rabc_error_kind(err) != RABC_ERROR_KIND_BUG) {
+        printf("Error: rabc_client_process_with_error() should fail on "
+               "invalid event\n");
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+    printf("Expected error: %s: %s\n",
+           rabc_error_kind_to_str(rabc_error_kind(err)), rabc_error_msg(err));
+    rabc_error_free(err);
+    err = NULL;
+
+    /* Connection of client3 should be notified to subscribed client2 */
+    rabc_client_set_reply_callback(client2, reply_callback, &reply_count);
+    rabc_client_set_notification_callback(client2, notification_callback,
+                                          &notification_count);
+    if (rabc_client_send(client2, "subscribe", &err) != RABC_PASS ||
+        rabc_client_run(client2, 1, &err) != RABC_PASS ||
+        rabc_client_new_with_error(&client3, &err) != RABC_PASS) {
+        printf("Error: %s: %s\n",
+               rabc_error_kind_to_str(rabc_error_kind(err)),
+               rabc_error_msg(err));
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+
+    /* Timer expires every 2 seconds, heartbeat reply should be dispatched */
+    if (rabc_client_run(client2, WAIT_TIME, &err) != RABC_PASS) {
+        printf("Error: %s: %s\n",
+               rabc_error_kind_to_str(rabc_error_kind(err)),
+               rabc_error_msg(err));
+        rc = EXIT_FAILURE;
+        goto out;
+    }
+    if (reply_count == 0) {
+        printf("Error: reply callback not invoked\n");
+        rc = EXIT_FAILURE;
+    }
+    if (notification_count == 0) {
+        printf("Error: notification callback not invoked\n");
+        rc = EXIT_FAILURE;
+    }
+
  out:
+    rabc_client_free(client3);
+    rabc_error_free(err);
+    rabc_client_free(client2);
     rabc_cstring_free(err_kind);
     rabc_cstring_free(err_msg);
     rabc_cstring_free(log);
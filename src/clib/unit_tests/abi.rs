@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::path::Path;
+
+// `<node> <symbol>` of exported functions generated by build.rs
+const SYMBOLS: &str = include_str!(concat!(env!("OUT_DIR"), "/rabc.symbols"));
+const HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/rabc.h"));
+
+// Symbol name to version node
+fn exported_symbols() -> HashMap<&'static str, &'static str> {
+    SYMBOLS
+        .lines()
+        .filter_map(|l| l.split_once(' '))
+        .map(|(node, symbol)| (symbol, node))
+        .collect()
+}
+
+// Function name to its prototype without comments and with whitespace
+// normalized, e.g. `void rabc_client_free(struct rabc_client*client)`
+fn prototypes(header: &str) -> HashMap<String, String> {
+    let mut code = String::new();
+    let mut rest = header;
+    while let Some((before, after)) = rest.split_once("/*") {
+        code.push_str(before);
+        rest = after.split_once("*/").map(|(_, a)| a).unwrap_or_default();
+    }
+    code.push_str(rest);
+    let mut lines = Vec::new();
+    let mut in_directive = false;
+    for line in code.lines() {
+        // Preprocessor directive might continue with trailing backslash
+        if in_directive || line.trim_start().starts_with('#') {
+            in_directive = line.ends_with('\\');
+        } else {
+            lines.push(line.split("//").next().unwrap_or_default());
+        }
+    }
+    let code = lines.join(" ");
+
+    let mut ret = HashMap::new();
+    for statement in code.split(';') {
+        let statement = statement.rsplit(['{', '}']).next().unwrap_or_default();
+        if statement.contains("typedef") {
+            continue;
+        }
+        let mut prototype = String::new();
+        for word in statement.split_whitespace() {
+            if !prototype.is_empty()
+                && !prototype.ends_with(['*', '(', ','])
+                && !word.starts_with(['*', ')', ','])
+            {
+                prototype.push(' ');
+            }
+            prototype.push_str(word);
+        }
+        let name = prototype
+            .split_once('(')
+            .and_then(|(before, _)| before.rsplit([' ', '*']).next());
+        if let Some(name) = name.filter(|n| !n.is_empty()) {
+            ret.insert(name.to_string(), prototype.clone());
+        }
+    }
+    ret
+}
+
+// Each `abi/rabc-<version>.symbols` holds the `nm -D --defined-only` output
+// without address of the released librabc.so, the symbols should never be
+// removed or moved out of the `RABC_<version>` node. The `make clib_check`
+// checks the same against dynamic symbols of the built librabc.so via
+// tests/abi_check.sh.
+#[test]
+fn test_abi_compatible_with_releases() {
+    let symbols = exported_symbols();
+    let abi_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("abi");
+    let mut release_count = 0;
+    for entry in std::fs::read_dir(abi_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("symbols") {
+            continue;
+        }
+        release_count += 1;
+        let version = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.strip_prefix("rabc-"))
+            .unwrap();
+        let node = format!("RABC_{}", version);
+        for line in std::fs::read_to_string(&path).unwrap().lines() {
+            let (_, symbol) = line.split_once(' ').unwrap();
+            assert_eq!(
+                symbols.get(symbol),
+                Some(&node.as_str()),
+                "Symbol {} of {} is not exported in {}",
+                symbol,
+                path.display(),
+                node
+            );
+        }
+    }
+    assert!(release_count > 0);
+}
+
+// Each `abi/rabc-<version>.h` is the header shipped by that release, the
+// prototypes of its functions should never change.
+#[test]
+fn test_prototypes_compatible_with_releases() {
+    let current = prototypes(HEADER);
+    let abi_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("abi");
+    let mut release_count = 0;
+    for entry in std::fs::read_dir(abi_dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|e| e.to_str()) != Some("h") {
+            continue;
+        }
+        release_count += 1;
+        let header = std::fs::read_to_string(&path).unwrap();
+        for (name, prototype) in prototypes(&header) {
+            // The 0.1.0 header declared `int` for the `uint32_t` returned,
+            // they are passed in the same register
+            let prototype = match prototype.strip_prefix("int ") {
+                Some(p) => format!("uint32_t {}", p),
+                None => prototype,
+            };
+            assert_eq!(
+                current.get(&name),
+                Some(&prototype),
+                "Prototype of {} changed since {}",
+                name,
+                path.display()
+            );
+        }
+    }
+    assert!(release_count > 0);
+}
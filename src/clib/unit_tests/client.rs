@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ffi::CStr;
+use std::io::{Read, Write};
+use std::os::raw::{c_char, c_void};
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+
+use rabc::{ErrorKind, RabcClient, RabcError, RabcEvent, IPC_HEADER_SIZE};
+
+use crate::client::{
+    rabc_client_run, rabc_client_set_notification_callback,
+    rabc_client_set_state_callback, RabcCClient, RabcClientState,
+};
+use crate::error::{
+    error_kind_to_u32, rabc_error_free, rabc_error_kind, rabc_error_msg,
+    RabcCError,
+};
+use crate::logger::with_captures_borrowed;
+use crate::{
+    call_with_error, rabc_bytes_free, rabc_client_free, rabc_client_new,
+    rabc_client_process, rabc_client_process_bytes_with_error,
+    rabc_client_process_with_error, rabc_cstring_free, RABC_FAIL, RABC_PASS,
+};
+
+// Daemon sending specified raw messages to the first client
+struct FakeDaemon {
+    path: PathBuf,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl FakeDaemon {
+    fn new(name: &str, messages: Vec<Vec<u8>>) -> Self {
+        Self::start(name, messages, true)
+    }
+
+    // Close the connection once messages sent
+    fn new_closing(name: &str, messages: Vec<Vec<u8>>) -> Self {
+        Self::start(name, messages, false)
+    }
+
+    fn start(name: &str, messages: Vec<Vec<u8>>, wait_client: bool) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "rabc_test_{}_{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::remove_file(&path).ok();
+        let listener = UnixListener::bind(&path).unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Reply the `protocol` request sent by client once connected
+            let mut header = [0u8; IPC_HEADER_SIZE];
+            stream.read_exact(&mut header).unwrap();
+            let mut request = vec![0u8; usize::from_ne_bytes(header)];
+            stream.read_exact(&mut request).unwrap();
+            let messages =
+                std::iter::once(br#"{"reply": "2"}"#.to_vec()).chain(messages);
+            for message in messages {
+                stream.write_all(&message.len().to_ne_bytes()).unwrap();
+                stream.write_all(&message).unwrap();
+            }
+            if wait_client {
+                stream.read_to_end(&mut Vec::new()).ok();
+            }
+        });
+        Self {
+            path,
+            handle: Some(handle),
+        }
+    }
+
+    fn connect(&self) -> *mut RabcCClient {
+        let client =
+            RabcClient::new_with_socket(self.path.to_str().unwrap()).unwrap();
+        Box::into_raw(Box::new(RabcCClient::new(client)))
+    }
+}
+
+impl Drop for FakeDaemon {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+// Process one message and return (rc, reply, err_kind, err_msg)
+fn process(
+    client: *mut RabcCClient,
+) -> (u32, Option<String>, Option<String>, Option<String>) {
+    let mut reply: *mut c_char = std::ptr::null_mut();
+    let mut log: *mut c_char = std::ptr::null_mut();
+    let mut err_kind: *mut c_char = std::ptr::null_mut();
+    let mut err_msg: *mut c_char = std::ptr::null_mut();
+    let rc = rabc_client_process(
+        client,
+        RabcEvent::IpcIn as u64,
+        &mut reply,
+        &mut log,
+        &mut err_kind,
+        &mut err_msg,
+    );
+    let to_string = |s: *mut c_char| {
+        if s.is_null() {
+            None
+        } else {
+            let ret =
+                unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+            rabc_cstring_free(s);
+            Some(ret)
+        }
+    };
+    rabc_cstring_free(log);
+    (
+        rc,
+        to_string(reply),
+        to_string(err_kind),
+        to_string(err_msg),
+    )
+}
+
+#[test]
+fn test_hostile_replies() {
+    let daemon = FakeDaemon::new(
+        "hostile_replies",
+        vec![
+            br#"{"reply": "a\u0000b"}"#.to_vec(),
+            br#"{"error": {"kind": "Bug", "msg": "c\u0000d"}}"#.to_vec(),
+            b"\xff\xfe\x00".to_vec(),
+            b"\x00not json".to_vec(),
+            br#"{"reply": "still working"}"#.to_vec(),
+            br#"{"reply": "e\u0000f"}"#.to_vec(),
+            br#"{"reply_bytes": [255, 254, 0]}"#.to_vec(),
+        ],
+    );
+    let client = daemon.connect();
+
+    let (rc, reply, err_kind, err_msg) = process(client);
+    assert_eq!(rc, RABC_FAIL);
+    assert_eq!(reply, None);
+    assert_eq!(err_kind.as_deref(), Some("ProtocolError"));
+    assert!(err_msg.unwrap().contains("rabc_client_process_bytes()"));
+
+    let (rc, reply, err_kind, err_msg) = process(client);
+    assert_eq!(rc, RABC_FAIL);
+    assert_eq!(reply, None);
+    assert_eq!(err_kind.as_deref(), Some("Bug"));
+    assert_eq!(err_msg.as_deref(), Some("c\\0d"));
+
+    for _ in 0..2 {
+        let (rc, reply, _, err_msg) = process(client);
+        assert_eq!(rc, RABC_FAIL);
+        assert_eq!(reply, None);
+        assert!(err_msg.is_some());
+    }
+
+    let mut reply: *mut c_char = std::ptr::null_mut();
+    let mut err: *mut RabcCError = std::ptr::null_mut();
+    assert_eq!(
+        rabc_client_process_with_error(
+            client,
+            RabcEvent::IpcIn as u64,
+            &mut reply,
+            &mut err
+        ),
+        RABC_PASS
+    );
+    assert!(err.is_null());
+    assert_eq!(
+        unsafe { CStr::from_ptr(reply) }.to_str().unwrap(),
+        "still working"
+    );
+    rabc_cstring_free(reply);
+
+    let mut reply: *mut u8 = std::ptr::null_mut();
+    let mut reply_len = 0u64;
+    assert_eq!(
+        rabc_client_process_bytes_with_error(
+            client,
+            RabcEvent::IpcIn as u64,
+            &mut reply,
+            &mut reply_len,
+            &mut err
+        ),
+        RABC_PASS
+    );
+    assert_eq!(
+        unsafe { std::slice::from_raw_parts(reply, reply_len as usize) },
+        b"e\0f"
+    );
+    rabc_bytes_free(reply, reply_len);
+
+    let (rc, _, err_kind, _) = process(client);
+    assert_eq!(rc, RABC_FAIL);
+    assert_eq!(err_kind.as_deref(), Some("ProtocolError"));
+    rabc_client_free(client);
+}
+
+#[test]
+fn test_panic_as_bug_error() {
+    let mut err: *mut RabcCError = std::ptr::null_mut();
+    let rc = call_with_error(&mut err, || -> Result<(), RabcError> {
+        panic!("hostile reply")
+    });
+    assert_eq!(rc, RABC_FAIL);
+    assert_eq!(rabc_error_kind(err), error_kind_to_u32(ErrorKind::Bug));
+    let msg = unsafe { CStr::from_ptr(rabc_error_msg(err)) };
+    assert!(msg.to_str().unwrap().contains("hostile reply"));
+    rabc_error_free(err);
+}
+
+#[test]
+fn test_exported_fn_panic_as_bug_error() {
+    let mut client: *mut RabcCClient = std::ptr::null_mut();
+    let mut log: *mut c_char = std::ptr::null_mut();
+    let mut err_kind: *mut c_char = std::ptr::null_mut();
+    let mut err_msg: *mut c_char = std::ptr::null_mut();
+    // Starting log capture panics as the captures are already borrowed
+    let rc = with_captures_borrowed(|| {
+        rabc_client_new(&mut client, &mut log, &mut err_kind, &mut err_msg)
+    });
+    assert_eq!(rc, RABC_FAIL);
+    assert!(client.is_null());
+    assert!(log.is_null());
+    let kind = unsafe { CStr::from_ptr(err_kind) }.to_str().unwrap();
+    assert_eq!(kind, "Bug");
+    let msg = unsafe { CStr::from_ptr(err_msg) }.to_str().unwrap();
+    assert!(msg.starts_with("Unexpected panic in librabc"));
+    rabc_cstring_free(err_kind);
+    rabc_cstring_free(err_msg);
+}
+
+#[derive(Default)]
+struct Callbacks {
+    notifications: Vec<String>,
+    states: Vec<(u32, u32)>,
+}
+
+extern "C" fn notification_callback(
+    notification: *const c_char,
+    userdata: *mut c_void,
+) {
+    let callbacks = unsafe { &mut *(userdata as *mut Callbacks) };
+    let notification = unsafe { CStr::from_ptr(notification) };
+    callbacks
+        .notifications
+        .push(notification.to_str().unwrap().to_string());
+}
+
+extern "C" fn state_callback(
+    state: u32,
+    err: *const RabcCError,
+    userdata: *mut c_void,
+) {
+    let callbacks = unsafe { &mut *(userdata as *mut Callbacks) };
+    callbacks.states.push((state, rabc_error_kind(err)));
+}
+
+#[test]
+fn test_run_callbacks() {
+    let daemon = FakeDaemon::new_closing(
+        "run_callbacks",
+        vec![
+            br#"{"notification": {"event": "client_disconnected", "id": 1}}"#
+                .to_vec(),
+        ],
+    );
+    let client = daemon.connect();
+    let mut callbacks = Callbacks::default();
+    let userdata = &mut callbacks as *mut Callbacks as *mut c_void;
+    rabc_client_set_notification_callback(
+        client,
+        Some(notification_callback),
+        userdata,
+    );
+    rabc_client_set_state_callback(client, Some(state_callback), userdata);
+
+    let mut err: *mut RabcCError = std::ptr::null_mut();
+    assert_eq!(rabc_client_run(client, 5, &mut err), RABC_FAIL);
+    assert_eq!(
+        rabc_error_kind(err),
+        error_kind_to_u32(ErrorKind::PeerClosed)
+    );
+    rabc_error_free(err);
+    assert_eq!(
+        callbacks.notifications,
+        vec![r#"{"event":"client_disconnected","id":1}"#.to_string()]
+    );
+    assert_eq!(
+        callbacks.states,
+        vec![(
+            RabcClientState::Disconnected as u32,
+            error_kind_to_u32(ErrorKind::PeerClosed)
+        )]
+    );
+    rabc_client_free(client);
+}
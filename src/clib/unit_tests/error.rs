@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::ffi::CStr;
+
+use rabc::{ErrorKind, RabcError};
+
+use crate::error::{
+    error_kind_to_u32, rabc_error_errno, rabc_error_free, rabc_error_kind,
+    rabc_error_kind_to_str, rabc_error_msg, RabcCError,
+};
+
+#[test]
+fn test_error_kind_value() {
+    let mut values: Vec<u32> = ErrorKind::ALL
+        .iter()
+        .map(|k| error_kind_to_u32(*k))
+        .collect();
+    values.sort_unstable();
+    let expected: Vec<u32> = (0..ErrorKind::ALL.len() as u32).collect();
+    assert_eq!(values, expected);
+}
+
+#[test]
+fn test_error_kind_to_str() {
+    for kind in ErrorKind::ALL {
+        let name = unsafe {
+            CStr::from_ptr(rabc_error_kind_to_str(error_kind_to_u32(kind)))
+        };
+        assert_eq!(name.to_str().unwrap(), kind.to_string());
+    }
+    let name = unsafe { CStr::from_ptr(rabc_error_kind_to_str(u32::MAX)) };
+    assert_eq!(name.to_str().unwrap(), "Unknown");
+}
+
+#[test]
+fn test_error_handle() {
+    let err = Box::into_raw(Box::new(RabcCError::from(
+        &RabcError::new(ErrorKind::ProtocolError, "bad\0reply".to_string())
+            .with_errno(32),
+    )));
+    assert_eq!(
+        rabc_error_kind(err),
+        error_kind_to_u32(ErrorKind::ProtocolError)
+    );
+    assert_eq!(rabc_error_errno(err), 32);
+    let msg = unsafe { CStr::from_ptr(rabc_error_msg(err)) };
+    assert_eq!(msg.to_str().unwrap(), "bad\\0reply");
+    rabc_error_free(err);
+
+    assert_eq!(
+        rabc_error_kind(std::ptr::null()),
+        error_kind_to_u32(ErrorKind::Bug)
+    );
+    assert!(rabc_error_msg(std::ptr::null()).is_null());
+}
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use log::Log;
+
+use crate::logger::{LogCapture, MemoryLogger};
+
+fn log_msg(msg: &str) {
+    MemoryLogger.log(
+        &log::Record::builder()
+            .args(format_args!("{}", msg))
+            .level(log::Level::Info)
+            .target("rabc::test")
+            .build(),
+    );
+}
+
+fn captured_msgs(logs: Option<String>) -> Vec<String> {
+    let logs: Vec<serde_json::Value> =
+        serde_json::from_str(&logs.unwrap()).unwrap();
+    logs.iter()
+        .map(|l| l["msg"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_log_capture_per_thread() {
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let capture = LogCapture::start();
+                for j in 0..100 {
+                    log_msg(&format!("{}-{}", i, j));
+                }
+                (i, captured_msgs(capture.finish()))
+            })
+        })
+        .collect();
+    for handle in handles {
+        let (i, msgs) = handle.join().unwrap();
+        let expected: Vec<String> =
+            (0..100).map(|j| format!("{}-{}", i, j)).collect();
+        assert_eq!(msgs, expected);
+    }
+}
+
+#[test]
+fn test_log_capture_nested() {
+    log_msg("discarded");
+    let outer = LogCapture::start();
+    log_msg("outer");
+    {
+        let inner = LogCapture::start();
+        log_msg("inner");
+        assert_eq!(captured_msgs(inner.finish()), vec!["inner"]);
+    }
+    log_msg("outer again");
+    assert_eq!(captured_msgs(outer.finish()), vec!["outer", "outer again"]);
+}
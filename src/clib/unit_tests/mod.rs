@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(test)]
+mod abi;
+#[cfg(test)]
+mod client;
+#[cfg(test)]
+mod error;
+#[cfg(test)]
+mod logger;
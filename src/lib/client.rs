@@ -1,29 +1,94 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use std::os::unix::io::AsRawFd;
+use std::collections::VecDeque;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
 
 use crate::{
     epoll::RabcEpoll, timer::RabcTimer, RabcConnection, RabcError, RabcEvent,
+    RabcMessage, RabcNotification, SOCKET_PATH,
 };
 
 const DEFAULT_TIMER_INTERVAL: u32 = 2; // send out ping every 2 seconds
+const HEARTBEAT_REQUEST: &str = "ping";
+
+#[derive(Debug)]
+struct PendingRequest {
+    request: String,
+    heartbeat: bool,
+    sent_time: Instant,
+}
+
+/// Reply from daemon paired with its request.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RabcReply {
+    /// The request of this reply, `None` if daemon sent error without
+    /// request.
+    pub request: Option<String>,
+    /// Whether the request is the heartbeat ping sent by timer
+    pub heartbeat: bool,
+    /// Round-trip time of the request
+    pub rtt: Option<Duration>,
+    /// Reply data which is UTF-8 text unless daemon replied
+    /// [RabcMessage::ReplyBytes]
+    pub result: Result<Vec<u8>, RabcError>,
+}
+
+impl RabcReply {
+    /// The reply as text, [crate::ErrorKind::ProtocolError] if it is not
+    /// valid UTF-8.
+    pub fn into_text(self) -> Result<String, RabcError> {
+        Ok(String::from_utf8(self.result?)?)
+    }
+}
+
+/// Message processed by [RabcClient::process_message()].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RabcClientMessage {
+    Reply(RabcReply),
+    Notification(RabcNotification),
+}
 
 #[derive(Debug)]
 pub struct RabcClient {
     timer: RabcTimer,
     conn: RabcConnection,
     epoll: RabcEpoll,
+    // Daemon replies in the order of requests
+    pending: VecDeque<PendingRequest>,
+    last_rtt: Option<Duration>,
+}
+
+/// The raw fd is the epoll fd which will be readable when [RabcClient::poll()]
+/// has events to return.
+impl AsRawFd for RabcClient {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll.fd
+    }
 }
 
 impl RabcClient {
     pub fn new() -> Result<Self, RabcError> {
+        Self::new_with_socket(SOCKET_PATH)
+    }
+
+    /// Connect to daemon listening on specified UNIX socket path.
+    pub fn new_with_socket(socket_path: &str) -> Result<Self, RabcError> {
         let epoll = RabcEpoll::new()?;
         let timer = RabcTimer::new(DEFAULT_TIMER_INTERVAL)?;
         epoll.add_fd(timer.as_raw_fd(), RabcEvent::Timer)?;
-        let conn = RabcConnection::connect()?;
+        let conn = RabcConnection::connect_to(socket_path)?;
         epoll.add_fd(conn.as_raw_fd(), RabcEvent::IpcIn)?;
 
-        Ok(Self { timer, conn, epoll })
+        Ok(Self {
+            timer,
+            conn,
+            epoll,
+            pending: VecDeque::new(),
+            last_rtt: None,
+        })
     }
 
     pub fn poll(
@@ -33,21 +98,138 @@ impl RabcClient {
         self.epoll.poll(wait_time)
     }
 
+    /// Send request to daemon, the reply will be returned by
+    /// [RabcClient::process()] or [RabcClient::process_message()] on
+    /// [RabcEvent::IpcIn].
+    pub fn send(&mut self, request: &str) -> Result<(), RabcError> {
+        self.send_request(request, false)
+    }
+
+    /// Ask daemon to stream its log records up to specified level, the
+    /// records are emitted to the `log` pipeline of current process with
+    /// target prefixed by [crate::DAEMON_LOG_TARGET_PREFIX].
+    /// [log::LevelFilter::Off] stops the streaming.
+    pub fn request_daemon_logs(
+        &mut self,
+        level: log::LevelFilter,
+    ) -> Result<(), RabcError> {
+        self.send(&format!("logs {}", level.as_str().to_lowercase()))
+    }
+
+    /// Round-trip time of the last replied heartbeat ping.
+    pub fn last_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
+    /// Process the event and return the reply text if any,
+    /// [crate::ErrorKind::ProtocolError] if reply is not valid UTF-8.
     pub fn process(
         &mut self,
         event: &RabcEvent,
     ) -> Result<Option<String>, RabcError> {
+        match self.process_bytes(event)? {
+            Some(data) => Ok(Some(String::from_utf8(data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as [RabcClient::process()] but return the reply as bytes.
+    pub fn process_bytes(
+        &mut self,
+        event: &RabcEvent,
+    ) -> Result<Option<Vec<u8>>, RabcError> {
+        match self.process_message(event)? {
+            Some(RabcClientMessage::Reply(reply)) => match reply.result {
+                Ok(r) => Ok(Some(r)),
+                Err(e) => {
+                    log::error!("Got error reply from daemon: {}", e);
+                    Err(e)
+                }
+            },
+            Some(RabcClientMessage::Notification(n)) => {
+                log::debug!("Got notification from daemon: {}", n);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Process the event and return the message from daemon if any.
+    /// Unlike [RabcClient::process()], error replied by daemon is included
+    /// in [RabcReply] instead of being returned as `Err`.
+    pub fn process_message(
+        &mut self,
+        event: &RabcEvent,
+    ) -> Result<Option<RabcClientMessage>, RabcError> {
         log::debug!("Processing event {:?}", event);
         match event {
             RabcEvent::Timer => {
                 self.timer.wait()?;
-                self.conn.ipc_send("ping")?;
+                self.send_request(HEARTBEAT_REQUEST, true)?;
                 Ok(None)
             }
+            // Daemon log records are emitted to the `log` pipeline
             RabcEvent::IpcIn => {
-                let reply = self.conn.ipc_recv()?;
-                Ok(Some(reply))
+                match self.conn.ipc_recv_message()?.apply_log() {
+                    Some(RabcMessage::Reply(reply)) => {
+                        Ok(Some(RabcClientMessage::Reply(
+                            self.pair_reply(Ok(reply.into_bytes())),
+                        )))
+                    }
+                    Some(RabcMessage::ReplyBytes(data)) => Ok(Some(
+                        RabcClientMessage::Reply(self.pair_reply(Ok(data))),
+                    )),
+                    Some(RabcMessage::Error(e)) => Ok(Some(
+                        RabcClientMessage::Reply(self.pair_reply(Err(e))),
+                    )),
+                    Some(RabcMessage::Notification(n)) => {
+                        Ok(Some(RabcClientMessage::Notification(n)))
+                    }
+                    Some(m) => {
+                        log::debug!("Ignoring unexpected message {:?}", m);
+                        Ok(None)
+                    }
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    fn send_request(
+        &mut self,
+        request: &str,
+        heartbeat: bool,
+    ) -> Result<(), RabcError> {
+        self.conn.ipc_send(request)?;
+        self.pending.push_back(PendingRequest {
+            request: request.to_string(),
+            heartbeat,
+            sent_time: Instant::now(),
+        });
+        Ok(())
+    }
+
+    fn pair_reply(&mut self, result: Result<Vec<u8>, RabcError>) -> RabcReply {
+        match self.pending.pop_front() {
+            Some(pending) => {
+                let rtt = pending.sent_time.elapsed();
+                if pending.heartbeat {
+                    log::debug!("Heartbeat round-trip time {:?}", rtt);
+                    self.last_rtt = Some(rtt);
+                }
+                RabcReply {
+                    request: Some(pending.request),
+                    heartbeat: pending.heartbeat,
+                    rtt: Some(rtt),
+                    result,
+                }
             }
+            None => RabcReply {
+                request: None,
+                heartbeat: false,
+                rtt: None,
+                result,
+            },
         }
     }
 }
@@ -22,7 +22,9 @@ impl RabcEpoll {
                 let e = RabcError::new(
                     ErrorKind::Bug,
                     format!("Failed to epoll_create(): {}", e),
-                );
+                )
+                .with_errno(e as i32)
+                .with_source(e);
                 log::error!("{}", e);
                 e
             })?,
@@ -48,7 +50,9 @@ impl RabcEpoll {
                         event,
                         e
                     ),
-                );
+                )
+                .with_errno(e as i32)
+                .with_source(e);
                 log::error!("{}", e);
                 e
             },
@@ -62,19 +66,24 @@ impl RabcEpoll {
         let mut events: [EpollEvent; EVENT_BUFFER_COUNT] =
             [EpollEvent::empty(); EVENT_BUFFER_COUNT];
 
-        let wait_time: isize = (1000 * wait_time).try_into().map_err(|e| {
-            RabcError::new(
-                ErrorKind::InvalidArgument,
-                format!("wait time too big {}: {}", wait_time, e),
-            )
-        })?;
+        let wait_time: isize = wait_time
+            .checked_mul(1000)
+            .and_then(|t| t.try_into().ok())
+            .ok_or_else(|| {
+                RabcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("wait time too big {}", wait_time),
+                )
+            })?;
 
         let changed_count = epoll_wait(self.fd, &mut events, wait_time)
             .map_err(|e| {
                 let e = RabcError::new(
                     ErrorKind::Bug,
                     format!("Failed on epoll_wait(): {}", e),
-                );
+                )
+                .with_errno(e as i32)
+                .with_source(e);
                 log::error!("{}", e);
                 e
             })?;
@@ -1,12 +1,55 @@
 // SPDX-License-Identifier: Apache-2.0
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum ErrorKind {
+    /// Failure of the IPC socket not covered by other kinds, e.g. a
+    /// component of the socket path is not a directory.
     IpcConnectionError,
     ExceededIpcMaxSize,
     InvalidArgument,
     Bug,
+    /// Daemon refused the connection or message as client exceeded the
+    /// connection or message rate limits.
+    Throttled,
+    PermissionDenied,
+    /// Timeout on sending or receiving data.
+    Timeout,
+    /// Daemon is not running or refused the connection.
+    NotConnected,
+    /// Peer sent data not following the rabc IPC protocol.
+    ProtocolError,
+    /// Peer closed the connection.
+    PeerClosed,
+}
+
+impl ErrorKind {
+    /// All kinds in the order of definition, new kinds are appended so
+    /// bindings could use the index as stable integer value.
+    pub const ALL: [Self; 10] = [
+        Self::IpcConnectionError,
+        Self::ExceededIpcMaxSize,
+        Self::InvalidArgument,
+        Self::Bug,
+        Self::Throttled,
+        Self::PermissionDenied,
+        Self::Timeout,
+        Self::NotConnected,
+        Self::ProtocolError,
+        Self::PeerClosed,
+    ];
+
+    /// Whether the connection is not usable any more.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(
+            self,
+            Self::IpcConnectionError | Self::NotConnected | Self::PeerClosed
+        )
+    }
 }
 
 impl std::fmt::Display for ErrorKind {
@@ -16,23 +59,101 @@ impl std::fmt::Display for ErrorKind {
 }
 
 impl std::fmt::Display for RabcError {
+    /// Format as `kind: outermost context: ...: msg` with details in the
+    /// next line.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.kind, self.msg)
+        write!(f, "{}: {}", self.kind, self.full_msg())
     }
 }
 
-impl std::error::Error for RabcError {}
+impl std::error::Error for RabcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn std::error::Error + 'static))
+    }
+}
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct RabcError {
     kind: ErrorKind,
     msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    errno: Option<i32>,
+    /// Innermost first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    context: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+    // Only available in the process generating the error
+    #[serde(skip)]
+    source: Option<BoxedError>,
+}
+
+/// The source error is not compared.
+impl PartialEq for RabcError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.msg == other.msg
+            && self.errno == other.errno
+            && self.context == other.context
+            && self.details == other.details
+    }
 }
 
+impl Eq for RabcError {}
+
 impl RabcError {
     pub fn new(kind: ErrorKind, msg: String) -> Self {
-        Self { kind, msg }
+        Self {
+            kind,
+            msg,
+            errno: None,
+            context: Vec::new(),
+            details: None,
+            source: None,
+        }
+    }
+
+    /// Set the underlying error causing this error.
+    pub fn with_source<E>(mut self, source: E) -> Self
+    where
+        E: Into<BoxedError>,
+    {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Set the raw OS error number.
+    pub fn with_errno(mut self, errno: i32) -> Self {
+        self.errno = Some(errno);
+        self
+    }
+
+    /// Push a context describing what was being done when the error
+    /// occurred, the last pushed one is the outermost.
+    pub fn with_context<S>(mut self, context: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.context.push(context.into());
+        self
+    }
+
+    /// Set additional information helping user to fix the error, e.g.
+    /// supported values of an invalid argument.
+    pub fn with_details<S>(mut self, details: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
     }
 
     pub fn kind(&self) -> ErrorKind {
@@ -42,16 +163,92 @@ impl RabcError {
     pub fn msg(&self) -> &str {
         self.msg.as_str()
     }
+
+    /// Raw OS error number if the error is caused by a system call.
+    pub fn errno(&self) -> Option<i32> {
+        self.errno
+    }
+
+    /// Context stack, innermost first.
+    pub fn context(&self) -> &[String] {
+        self.context.as_slice()
+    }
+
+    pub fn details(&self) -> Option<&str> {
+        self.details.as_deref()
+    }
+
+    /// Error message prefixed by context stack, outermost first, with
+    /// details in the next line. Used by bindings where the kind is
+    /// stored separately.
+    pub fn full_msg(&self) -> String {
+        let mut msg = String::new();
+        for context in self.context.iter().rev() {
+            msg.push_str(context);
+            msg.push_str(": ");
+        }
+        msg.push_str(&self.msg);
+        if let Some(details) = self.details.as_ref() {
+            msg.push('\n');
+            msg.push_str(details);
+        }
+        msg
+    }
+}
+
+impl From<std::io::ErrorKind> for ErrorKind {
+    fn from(kind: std::io::ErrorKind) -> Self {
+        match kind {
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            // Socket read/write timeout is reported as EAGAIN
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                Self::Timeout
+            }
+            std::io::ErrorKind::NotConnected
+            | std::io::ErrorKind::ConnectionRefused => Self::NotConnected,
+            std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::UnexpectedEof => Self::PeerClosed,
+            std::io::ErrorKind::InvalidData => Self::ProtocolError,
+            std::io::ErrorKind::InvalidInput => Self::InvalidArgument,
+            _ => Self::Bug,
+        }
+    }
 }
 
 impl From<std::io::Error> for RabcError {
     fn from(e: std::io::Error) -> Self {
-        Self::new(ErrorKind::Bug, format!("std::io::Error: {}", e))
+        let mut error = Self::new(e.kind().into(), e.to_string());
+        if let Some(errno) = e.raw_os_error() {
+            error = error.with_errno(errno);
+        }
+        error.with_source(e)
+    }
+}
+
+impl From<nix::errno::Errno> for RabcError {
+    fn from(e: nix::errno::Errno) -> Self {
+        RabcError::from(std::io::Error::from(e))
+    }
+}
+
+impl From<serde_json::Error> for RabcError {
+    fn from(e: serde_json::Error) -> Self {
+        let kind = match e.classify() {
+            serde_json::error::Category::Io => ErrorKind::Bug,
+            _ => ErrorKind::ProtocolError,
+        };
+        Self::new(kind, format!("serde_json::Error: {}", e)).with_source(e)
     }
 }
 
 impl From<std::string::FromUtf8Error> for RabcError {
     fn from(e: std::string::FromUtf8Error) -> Self {
-        Self::new(ErrorKind::Bug, format!("std::string::FromUtf8Error: {}", e))
+        Self::new(
+            ErrorKind::ProtocolError,
+            format!("std::string::FromUtf8Error: {}", e),
+        )
+        .with_source(e)
     }
 }
@@ -3,11 +3,21 @@
 use std::io::{Read, Write};
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::os::unix::net::UnixStream;
+use std::time::Duration;
 
-use crate::{ErrorKind, RabcError};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{ErrorKind, RabcError, RabcMessage};
 
 pub const SOCKET_PATH: &str = "/tmp/librabc";
+/// Size of the data length header prefixed to each IPC message.
+pub const IPC_HEADER_SIZE: usize = std::mem::size_of::<usize>();
+/// Version of the [RabcMessage] JSON protocol, stated by client with the
+/// `protocol <version>` request once connected. Daemon replies in plain text
+/// of protocol version 1 to clients not stating it, e.g. librabc 0.1.
+pub const IPC_PROTOCOL_VERSION: u32 = 2;
 const DEFAULT_MAX_DATA_SIZE: usize = 1024 * 1024; // 1 MiB
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
 pub struct RabcConnection {
@@ -23,25 +33,67 @@ impl AsRawFd for RabcConnection {
 
 impl RabcConnection {
     pub fn connect() -> Result<Self, RabcError> {
-        let stream = UnixStream::connect(SOCKET_PATH).map_err(|e| {
-            RabcError::new(
-                ErrorKind::InvalidArgument,
-                format!("Failed to connect socket {}: {}", SOCKET_PATH, e),
-            )
+        Self::connect_to(SOCKET_PATH)
+    }
+
+    /// Connect to daemon listening on specified UNIX socket path.
+    pub fn connect_to(socket_path: &str) -> Result<Self, RabcError> {
+        let stream = UnixStream::connect(socket_path).map_err(|e| {
+            // Socket file not found means daemon is not running
+            let not_found = e.kind() == std::io::ErrorKind::NotFound;
+            let e = ipc_io_error(e).with_context(format!(
+                "Failed to connect socket {}",
+                socket_path
+            ));
+            if not_found {
+                e.with_kind(ErrorKind::NotConnected)
+            } else {
+                e
+            }
         })?;
         log::debug!("Connected to Rabc daemon {}", stream.as_raw_fd());
-        Ok(Self {
+        let mut conn = Self {
             stream,
             max_size: DEFAULT_MAX_DATA_SIZE,
-        })
+        };
+        conn.handshake()?;
+        Ok(conn)
+    }
+
+    // State the protocol version to daemon, error replied by daemon, e.g.
+    // refused connection, is returned here.
+    fn handshake(&mut self) -> Result<(), RabcError> {
+        self.set_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let reply = self
+            .request(&format!("protocol {}", IPC_PROTOCOL_VERSION))
+            .map_err(|e| {
+                // Daemon of protocol version 1 replies plain text
+                if e.kind() == ErrorKind::ProtocolError {
+                    e.with_context(format!(
+                        "Daemon does not support protocol version {}",
+                        IPC_PROTOCOL_VERSION
+                    ))
+                } else {
+                    e
+                }
+            })?;
+        if reply != IPC_PROTOCOL_VERSION.to_string() {
+            return Err(RabcError::new(
+                ErrorKind::ProtocolError,
+                format!(
+                    "Expecting protocol version {} but daemon replied '{}'",
+                    IPC_PROTOCOL_VERSION, reply
+                ),
+            ));
+        }
+        self.set_timeout(None)?;
+        Ok(())
     }
 
     pub fn new(stream: UnixStream) -> Result<Self, RabcError> {
         stream.set_nonblocking(false).map_err(|e| {
-            RabcError::new(
-                ErrorKind::Bug,
-                format!("Failed to set UnixStream socket as blocking: {}", e),
-            )
+            RabcError::from(e)
+                .with_context("Failed to set UnixStream socket as blocking")
         })?;
         Ok(Self {
             stream,
@@ -60,43 +112,219 @@ impl RabcConnection {
         self.max_size
     }
 
+    /// Set the timeout of IPC send and receive, `None` means blocking
+    /// forever.
+    pub fn set_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<&mut Self, RabcError> {
+        self.stream
+            .set_read_timeout(timeout)
+            .and_then(|_| self.stream.set_write_timeout(timeout))
+            .map_err(|e| {
+                RabcError::from(e).with_context(format!(
+                    "Failed to set timeout {:?}",
+                    timeout
+                ))
+            })?;
+        Ok(self)
+    }
+
     pub fn ipc_recv(&mut self) -> Result<String, RabcError> {
-        let mut data_len_bytes = 0usize.to_ne_bytes();
-        if let Err(e) = self.stream.read_exact(&mut data_len_bytes) {
-            return Err(RabcError::new(
-                ErrorKind::IpcConnectionError,
-                format!("Failed to receive data size: {}", e),
-            ));
-        }
-        let data_len = usize::from_ne_bytes(data_len_bytes);
-        if data_len >= self.max_size {
-            return Err(RabcError::new(
-                ErrorKind::ExceededIpcMaxSize,
-                format!(
-                    "Received data exceeded the max size {} bytes, \
-                     please change the limitation by set_ipc_max_size()",
-                    self.max_size
-                ),
-            ));
-        }
+        Ok(String::from_utf8(self.ipc_recv_bytes()?)?)
+    }
+
+    /// Receive data without requiring it to be valid UTF-8.
+    pub fn ipc_recv_bytes(&mut self) -> Result<Vec<u8>, RabcError> {
+        let mut header = [0u8; IPC_HEADER_SIZE];
+        self.stream.read_exact(&mut header).map_err(|e| {
+            ipc_io_error(e).with_context("Failed to receive data size")
+        })?;
+        let data_len = decode_header(header, self.max_size)?;
         let mut data = vec![0u8; data_len];
-        self.stream.read_exact(data.as_mut_slice())?;
-        Ok(String::from_utf8(data)?)
+        self.stream
+            .read_exact(data.as_mut_slice())
+            .map_err(recv_error)?;
+        Ok(data)
+    }
+
+    pub fn ipc_recv_message(&mut self) -> Result<RabcMessage, RabcError> {
+        RabcMessage::from_json_bytes(&self.ipc_recv_bytes()?)
+    }
+
+    /// Send request to daemon and wait for its reply. Daemon log records
+    /// received meanwhile are emitted to the `log` pipeline.
+    pub fn request(&mut self, request: &str) -> Result<String, RabcError> {
+        self.ipc_send(request)?;
+        loop {
+            match self.ipc_recv_message()?.apply_log() {
+                Some(RabcMessage::Reply(reply)) => return Ok(reply),
+                Some(RabcMessage::ReplyBytes(data)) => {
+                    return Ok(String::from_utf8(data)?)
+                }
+                Some(RabcMessage::Error(e)) => return Err(e),
+                Some(m) => {
+                    return Err(RabcError::new(
+                        ErrorKind::ProtocolError,
+                        format!("Expecting reply but got {:?}", m),
+                    ))
+                }
+                None => (),
+            }
+        }
     }
 
     pub fn ipc_send(&mut self, data: &str) -> Result<(), RabcError> {
-        if data.len() > self.max_size {
-            return Err(RabcError::new(
-                ErrorKind::ExceededIpcMaxSize,
-                format!(
-                    "Specified data exceeded the max size {} bytes, \
-                        please change the limitation by set_ipc_max_size()",
-                    self.max_size
-                ),
-            ));
+        let header = encode_header(data.len(), self.max_size)?;
+        self.stream
+            .write_all(&header)
+            .and_then(|_| self.stream.write_all(data.as_bytes()))
+            .map_err(send_error)
+    }
+}
+
+/// The asynchronous version of [RabcConnection] used by daemon.
+#[derive(Debug)]
+pub struct RabcAsyncConnection {
+    stream: tokio::net::UnixStream,
+    max_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl AsRawFd for RabcAsyncConnection {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream.as_raw_fd()
+    }
+}
+
+impl RabcAsyncConnection {
+    pub fn new(stream: tokio::net::UnixStream) -> Self {
+        Self {
+            stream,
+            max_size: DEFAULT_MAX_DATA_SIZE,
+            buffer: Vec::new(),
         }
-        self.stream.write_all(&data.len().to_ne_bytes())?;
-        self.stream.write_all(data.as_bytes())?;
-        Ok(())
+    }
+
+    /// Set the max data size for IPC communication.
+    pub fn set_ipc_max_size(&mut self, max_size: usize) -> &mut Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Get the max data size for IPC communication.
+    pub fn get_ipc_max_size(&mut self) -> usize {
+        self.max_size
+    }
+
+    /// Receive a message from peer.
+    /// This function is cancel safe, partially received data is buffered
+    /// for next call, hence could be used in `tokio::select!`.
+    pub async fn ipc_recv(&mut self) -> Result<String, RabcError> {
+        loop {
+            if let Some(data) = self.take_buffered_data()? {
+                return Ok(String::from_utf8(data)?);
+            }
+            self.buffer.reserve(IPC_HEADER_SIZE);
+            match self.stream.read_buf(&mut self.buffer).await {
+                Ok(0) => {
+                    return Err(RabcError::new(
+                        ErrorKind::PeerClosed,
+                        "Connection closed by peer".to_string(),
+                    ));
+                }
+                Ok(_) => (),
+                Err(e) => return Err(recv_error(e)),
+            }
+        }
+    }
+
+    fn take_buffered_data(&mut self) -> Result<Option<Vec<u8>>, RabcError> {
+        if self.buffer.len() < IPC_HEADER_SIZE {
+            return Ok(None);
+        }
+        let mut header = [0u8; IPC_HEADER_SIZE];
+        header.copy_from_slice(&self.buffer[..IPC_HEADER_SIZE]);
+        let data_len = decode_header(header, self.max_size)?;
+        if self.buffer.len() < IPC_HEADER_SIZE + data_len {
+            self.buffer
+                .reserve(IPC_HEADER_SIZE + data_len - self.buffer.len());
+            return Ok(None);
+        }
+        let data =
+            self.buffer[IPC_HEADER_SIZE..IPC_HEADER_SIZE + data_len].to_vec();
+        self.buffer.drain(..IPC_HEADER_SIZE + data_len);
+        Ok(Some(data))
+    }
+
+    pub async fn ipc_send(&mut self, data: &str) -> Result<(), RabcError> {
+        let header = encode_header(data.len(), self.max_size)?;
+        let result = match self.stream.write_all(&header).await {
+            Ok(()) => self.stream.write_all(data.as_bytes()).await,
+            Err(e) => Err(e),
+        };
+        result.map_err(send_error)
+    }
+
+    pub async fn ipc_send_message(
+        &mut self,
+        message: &RabcMessage,
+    ) -> Result<(), RabcError> {
+        self.ipc_send(&message.to_json()?).await
+    }
+}
+
+// Shared by [RabcConnection] and [RabcAsyncConnection] to keep the wire
+// format and size limit identical.
+fn encode_header(
+    data_len: usize,
+    max_size: usize,
+) -> Result<[u8; IPC_HEADER_SIZE], RabcError> {
+    if data_len > max_size {
+        return Err(RabcError::new(
+            ErrorKind::ExceededIpcMaxSize,
+            format!(
+                "Specified data exceeded the max size {} bytes, \
+                 please change the limitation by set_ipc_max_size()",
+                max_size
+            ),
+        ));
+    }
+    Ok(data_len.to_ne_bytes())
+}
+
+fn decode_header(
+    header: [u8; IPC_HEADER_SIZE],
+    max_size: usize,
+) -> Result<usize, RabcError> {
+    let data_len = usize::from_ne_bytes(header);
+    if data_len > max_size {
+        return Err(RabcError::new(
+            ErrorKind::ExceededIpcMaxSize,
+            format!(
+                "Received data exceeded the max size {} bytes, \
+                 please change the limitation by set_ipc_max_size()",
+                max_size
+            ),
+        ));
+    }
+    Ok(data_len)
+}
+
+fn send_error(e: std::io::Error) -> RabcError {
+    ipc_io_error(e).with_context("Failed to send data")
+}
+
+fn recv_error(e: std::io::Error) -> RabcError {
+    ipc_io_error(e).with_context("Failed to receive data")
+}
+
+// Socket failure without more specific kind is IpcConnectionError
+fn ipc_io_error(e: std::io::Error) -> RabcError {
+    let e = RabcError::from(e);
+    if e.kind() == ErrorKind::Bug {
+        e.with_kind(ErrorKind::IpcConnectionError)
+    } else {
+        e
     }
 }
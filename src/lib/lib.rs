@@ -5,10 +5,22 @@ mod epoll;
 mod error;
 mod event;
 mod ipc;
+mod logging;
+mod message;
+mod status;
 mod timer;
 mod unit_tests;
 
-pub use crate::client::RabcClient;
+pub use crate::client::{RabcClient, RabcClientMessage, RabcReply};
 pub use crate::error::{ErrorKind, RabcError};
 pub use crate::event::RabcEvent;
-pub use crate::ipc::{RabcConnection, SOCKET_PATH};
+pub use crate::ipc::{
+    RabcAsyncConnection, RabcConnection, IPC_HEADER_SIZE, IPC_PROTOCOL_VERSION,
+    SOCKET_PATH,
+};
+pub use crate::logging::{
+    daemon_requested_log_level, format_rfc3339, RabcLogRecord,
+    DAEMON_LOG_TARGET_PREFIX,
+};
+pub use crate::message::{RabcMessage, RabcNotification};
+pub use crate::status::{RabcClientInfo, RabcStatus};
@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the target when a daemon log record is emitted to the `log`
+/// pipeline of client, e.g. `rabc::daemon::rabcd::limits`.
+pub const DAEMON_LOG_TARGET_PREFIX: &str = "rabc::daemon::";
+
+/// Log record in JSON used by bindings and daemon log streaming requested
+/// by the `logs <level>` request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RabcLogRecord {
+    /// UTC time in RFC 3339 format
+    pub time: String,
+    pub level: log::Level,
+    pub target: String,
+    pub msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Name of the logging thread, or its ID if unnamed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    /// Key-value fields, e.g. `log::warn!(client_pid = pid; "...")`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl RabcLogRecord {
+    pub fn from_record(record: &log::Record, time: SystemTime) -> Self {
+        struct Collector(BTreeMap<String, String>);
+
+        impl<'kvs> log::kv::VisitSource<'kvs> for Collector {
+            fn visit_pair(
+                &mut self,
+                key: log::kv::Key<'kvs>,
+                value: log::kv::Value<'kvs>,
+            ) -> Result<(), log::kv::Error> {
+                self.0.insert(key.to_string(), value.to_string());
+                Ok(())
+            }
+        }
+
+        let mut collector = Collector(BTreeMap::new());
+        record.key_values().visit(&mut collector).ok();
+        Self {
+            time: format_rfc3339(time),
+            level: record.level(),
+            target: record.target().to_string(),
+            msg: record.args().to_string(),
+            module: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+            thread: Some(current_thread_name()),
+            fields: collector.0,
+        }
+    }
+
+    /// Emit this record to the `log` pipeline of current process with
+    /// target prefixed by [DAEMON_LOG_TARGET_PREFIX].
+    pub fn emit(&self) {
+        let target = format!("{}{}", DAEMON_LOG_TARGET_PREFIX, self.target);
+        let metadata = log::Metadata::builder()
+            .level(self.level)
+            .target(&target)
+            .build();
+        let logger = log::logger();
+        if self.level > log::max_level() || !logger.enabled(&metadata) {
+            return;
+        }
+        let kvs: Vec<(&str, &str)> = self
+            .fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        logger.log(
+            &log::Record::builder()
+                .metadata(metadata)
+                .args(format_args!("{}", self.msg))
+                .module_path(self.module.as_deref())
+                .file(self.file.as_deref())
+                .line(self.line)
+                .key_values(&kvs.as_slice())
+                .build(),
+        );
+    }
+}
+
+// Level requested by daemon, `usize::MAX` when not requested
+static DAEMON_REQUESTED_LEVEL: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Log level of `rabc::` targets requested by daemon for this process,
+/// `None` if never requested. The global `log::max_level()` chosen by
+/// application is not changed, it is up to the logger to apply this level.
+pub fn daemon_requested_log_level() -> Option<log::LevelFilter> {
+    log::LevelFilter::iter().nth(DAEMON_REQUESTED_LEVEL.load(Ordering::Relaxed))
+}
+
+pub(crate) fn set_daemon_requested_log_level(level: log::LevelFilter) {
+    DAEMON_REQUESTED_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+fn current_thread_name() -> String {
+    let thread = std::thread::current();
+    match thread.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", thread.id()),
+    }
+}
+
+/// UTC time in RFC 3339 format with microseconds, e.g.
+/// `2022-07-01T08:00:00.000000Z`.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let duration = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = duration.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        duration.subsec_micros()
+    )
+}
+
+// Convert days since epoch to (year, month, day), algorithm from
+// http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
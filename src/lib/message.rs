@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use crate::logging::set_daemon_requested_log_level;
+use crate::{RabcClientInfo, RabcError, RabcLogRecord};
+
+/// Message sent from daemon to client, serialized as JSON, e.g.
+/// `{"reply": "pong"}` or
+/// `{"error": {"kind": "InvalidArgument", "msg": "...", "details": "..."}}`.
+/// The `errno`, `context` and `details` of error are omitted when empty.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RabcMessage {
+    /// Reply to client request
+    Reply(String),
+    /// Reply holding binary data serialized as array of bytes, e.g.
+    /// `{"reply_bytes": [255, 254]}`
+    ReplyBytes(Vec<u8>),
+    /// Daemon failed to process the client request
+    Error(RabcError),
+    /// Event sent to clients subscribed by the `subscribe` request
+    Notification(RabcNotification),
+    /// Daemon log record sent to clients requested it by the
+    /// `logs <level>` request
+    Log(RabcLogRecord),
+    /// Daemon asks client to change the log level of `rabc::` targets,
+    /// see [crate::daemon_requested_log_level()]
+    SetLogLevel(log::LevelFilter),
+}
+
+impl RabcMessage {
+    pub fn to_json(&self) -> Result<String, RabcError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self, RabcError> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    pub fn from_json_bytes(data: &[u8]) -> Result<Self, RabcError> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    /// Apply the log related message to the `log` pipeline of current
+    /// process, return the message back if it is not log related.
+    pub fn apply_log(self) -> Option<Self> {
+        match self {
+            Self::Log(record) => {
+                record.emit();
+                None
+            }
+            Self::SetLogLevel(level) => {
+                set_daemon_requested_log_level(level);
+                log::info!("Log level changed to {} by daemon", level);
+                None
+            }
+            m => Some(m),
+        }
+    }
+}
+
+/// Event notified to subscribed clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RabcNotification {
+    /// New client connected to daemon, subscribers other than root and the
+    /// daemon user are only notified about clients of their own user ID
+    ClientConnected { client: RabcClientInfo },
+    /// Client with specified ID disconnected from daemon
+    ClientDisconnected { id: u64 },
+}
+
+impl std::fmt::Display for RabcNotification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ClientConnected { client } => write!(
+                f,
+                "client {} connected, pid {}, uid {}",
+                client.id,
+                client
+                    .pid
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                client.uid
+            ),
+            Self::ClientDisconnected { id } => {
+                write!(f, "client {} disconnected", id)
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::RabcError;
+
+/// Reply of the `status` request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RabcStatus {
+    /// Version of the daemon
+    pub version: String,
+    /// Seconds since daemon started
+    pub uptime: u64,
+    /// Count of currently connected clients
+    pub connection_count: usize,
+    /// Max connections allowed by daemon, 0 means unlimited
+    pub max_connections: usize,
+    /// Max connections allowed per user ID, 0 means unlimited
+    pub max_connections_per_uid: usize,
+    /// Count of currently connected clients indexed by user ID, only the
+    /// requester's own user ID unless requested by root or the daemon user
+    pub connections_per_uid: BTreeMap<u32, usize>,
+    /// Count of connections refused due to connection limits
+    pub rejected_connection_count: u64,
+    /// Count of messages refused due to message rate limit
+    pub throttled_message_count: u64,
+    /// Currently connected clients, only those of requester's own user ID
+    /// unless requested by root or the daemon user
+    pub clients: Vec<RabcClientInfo>,
+}
+
+impl RabcStatus {
+    pub fn to_json(&self) -> Result<String, RabcError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self, RabcError> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+/// Information of a client connected to daemon.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RabcClientInfo {
+    /// Daemon assigned ID of this connection
+    pub id: u64,
+    /// Process ID of client, `None` if unknown
+    pub pid: Option<i32>,
+    /// User ID of client
+    pub uid: u32,
+    /// Seconds since UNIX epoch when client connected
+    pub connect_time: u64,
+    /// Count of messages received from this client
+    pub messages_received: u64,
+    /// Count of messages sent to this client
+    pub messages_sent: u64,
+    /// Bytes received from this client including IPC header
+    pub bytes_received: u64,
+    /// Bytes sent to this client including IPC header
+    pub bytes_sent: u64,
+}
@@ -27,7 +27,9 @@ impl RabcTimer {
                 let e = RabcError::new(
                     ErrorKind::Bug,
                     format!("Failed to create timerfd {}", e),
-                );
+                )
+                .with_errno(e as i32)
+                .with_source(e);
                 log::error!("{}", e);
                 e
             })?;
@@ -39,7 +41,9 @@ impl RabcTimer {
             let e = RabcError::new(
                 ErrorKind::Bug,
                 format!("Failed to set timerfd {}", e),
-            );
+            )
+            .with_errno(e as i32)
+            .with_source(e);
             log::error!("{}", e);
             e
         })?;
@@ -52,7 +56,9 @@ impl RabcTimer {
             let e = RabcError::new(
                 ErrorKind::Bug,
                 format!("Failed to wait timerfd {}", e),
-            );
+            )
+            .with_errno(e as i32)
+            .with_source(e);
             log::error!("{}", e);
             Err(e)
         } else {
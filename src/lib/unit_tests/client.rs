@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::{ErrorKind, RabcClient, RabcEvent, IPC_HEADER_SIZE};
+
+// Reply the `protocol` request sent by client once connected
+fn accept_handshake(stream: &mut UnixStream) {
+    let mut header = [0u8; IPC_HEADER_SIZE];
+    stream.read_exact(&mut header).unwrap();
+    let mut request = vec![0u8; usize::from_ne_bytes(header)];
+    stream.read_exact(&mut request).unwrap();
+    assert_eq!(request, b"protocol 2");
+    let reply = br#"{"reply": "2"}"#;
+    stream.write_all(&reply.len().to_ne_bytes()).unwrap();
+    stream.write_all(reply).unwrap();
+}
+
+#[test]
+fn test_process_reply_bytes() {
+    let path = std::env::temp_dir()
+        .join(format!("rabc_client_test_{}", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let listener = UnixListener::bind(&path).unwrap();
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        accept_handshake(&mut stream);
+        for _ in 0..2 {
+            let message = br#"{"reply_bytes": [255, 254]}"#;
+            stream.write_all(&message.len().to_ne_bytes()).unwrap();
+            stream.write_all(message).unwrap();
+        }
+        stream.read_to_end(&mut Vec::new()).ok();
+    });
+    let mut client =
+        RabcClient::new_with_socket(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(
+        client.process_bytes(&RabcEvent::IpcIn).unwrap(),
+        Some(b"\xff\xfe".to_vec())
+    );
+    assert_eq!(
+        client.process(&RabcEvent::IpcIn).unwrap_err().kind(),
+        ErrorKind::ProtocolError
+    );
+    drop(client);
+    handle.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
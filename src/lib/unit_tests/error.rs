@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::error::Error;
+
+use nix::errno::Errno;
+
+use crate::{ErrorKind, RabcError, RabcMessage};
+
+#[test]
+fn test_io_error_kind_and_errno() {
+    let cases = [
+        (Errno::EPIPE as i32, ErrorKind::PeerClosed),
+        (Errno::ECONNRESET as i32, ErrorKind::PeerClosed),
+        (Errno::EACCES as i32, ErrorKind::PermissionDenied),
+        (Errno::EAGAIN as i32, ErrorKind::Timeout),
+        (Errno::ECONNREFUSED as i32, ErrorKind::NotConnected),
+        (Errno::ENOMEM as i32, ErrorKind::Bug),
+    ];
+    for (errno, kind) in cases {
+        let e = RabcError::from(std::io::Error::from_raw_os_error(errno));
+        assert_eq!(e.kind(), kind, "errno {}", errno);
+        assert_eq!(e.errno(), Some(errno));
+    }
+}
+
+#[test]
+fn test_error_source_and_context() {
+    let e =
+        RabcError::from(std::io::Error::from_raw_os_error(Errno::EPIPE as i32))
+            .with_context("Failed to send data")
+            .with_context("Failed to send ping");
+
+    let source = e.source().unwrap();
+    let io_error = source.downcast_ref::<std::io::Error>().unwrap();
+    assert_eq!(io_error.raw_os_error(), Some(Errno::EPIPE as i32));
+    assert_eq!(e.context(), ["Failed to send data", "Failed to send ping"]);
+    assert_eq!(
+        e.to_string(),
+        format!(
+            "PeerClosed: Failed to send ping: Failed to send data: {}",
+            io_error
+        )
+    );
+
+    let plain = RabcError::new(ErrorKind::Bug, "bug".to_string());
+    assert!(plain.source().is_none());
+    assert_eq!(plain.to_string(), "Bug: bug");
+}
+
+#[test]
+fn test_error_serialize_without_source() {
+    let e = RabcError::from(std::io::Error::from_raw_os_error(
+        Errno::EACCES as i32,
+    ))
+    .with_context("Failed to connect");
+    let json = serde_json::to_string(&e).unwrap();
+    let parsed: RabcError = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, e);
+    assert!(parsed.source().is_none());
+
+    // Errors from older daemon have no errno or context
+    let parsed: RabcError =
+        serde_json::from_str(r#"{"kind":"Throttled","msg":"slow down"}"#)
+            .unwrap();
+    assert_eq!(parsed.errno(), None);
+    assert!(parsed.context().is_empty());
+}
+
+#[test]
+fn test_daemon_error_reply() {
+    let e = RabcError::new(ErrorKind::InvalidArgument, "Unknown".to_string())
+        .with_details("Supported commands: ping");
+    let json = RabcMessage::Error(e).to_json().unwrap();
+    assert_eq!(
+        json,
+        r#"{"error":{"kind":"InvalidArgument","msg":"Unknown","details":"Supported commands: ping"}}"#
+    );
+    let e = match RabcMessage::from_json(&json).unwrap() {
+        RabcMessage::Error(e) => e,
+        m => panic!("Expecting error, got {:?}", m),
+    };
+    assert_eq!(e.details(), Some("Supported commands: ping"));
+    assert_eq!(e.full_msg(), "Unknown\nSupported commands: ping");
+    assert_eq!(
+        e.to_string(),
+        "InvalidArgument: Unknown\nSupported commands: ping"
+    );
+}
+
+#[test]
+fn test_error_kind_all() {
+    // Exhaustive match fails to build when new kind is added, reminding
+    // to append it to ErrorKind::ALL
+    let index = |kind: ErrorKind| match kind {
+        ErrorKind::IpcConnectionError => 0,
+        ErrorKind::ExceededIpcMaxSize => 1,
+        ErrorKind::InvalidArgument => 2,
+        ErrorKind::Bug => 3,
+        ErrorKind::Throttled => 4,
+        ErrorKind::PermissionDenied => 5,
+        ErrorKind::Timeout => 6,
+        ErrorKind::NotConnected => 7,
+        ErrorKind::ProtocolError => 8,
+        ErrorKind::PeerClosed => 9,
+    };
+    for (i, kind) in ErrorKind::ALL.iter().enumerate() {
+        assert_eq!(index(*kind), i);
+    }
+}
@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::io::Write;
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use crate::{ErrorKind, RabcConnection};
+
+#[test]
+fn test_ipc_recv_bytes() {
+    let (mut peer, stream) = UnixStream::pair().unwrap();
+    let mut conn = RabcConnection::new(stream).unwrap();
+    let data = b"\xff\x00binary";
+    for _ in 0..2 {
+        peer.write_all(&data.len().to_ne_bytes()).unwrap();
+        peer.write_all(data).unwrap();
+    }
+
+    assert_eq!(conn.ipc_recv_bytes().unwrap(), data);
+    assert_eq!(
+        conn.ipc_recv().unwrap_err().kind(),
+        ErrorKind::ProtocolError
+    );
+}
+
+#[test]
+fn test_ipc_max_size() {
+    let (peer, stream) = UnixStream::pair().unwrap();
+    let mut conn = RabcConnection::new(stream).unwrap();
+    let mut peer = RabcConnection::new(peer).unwrap();
+    conn.set_ipc_max_size(4);
+    peer.set_ipc_max_size(5);
+
+    peer.ipc_send("1234").unwrap();
+    assert_eq!(conn.ipc_recv().unwrap(), "1234");
+    peer.ipc_send("12345").unwrap();
+    assert_eq!(
+        conn.ipc_recv().unwrap_err().kind(),
+        ErrorKind::ExceededIpcMaxSize
+    );
+    assert_eq!(
+        conn.ipc_send("12345").unwrap_err().kind(),
+        ErrorKind::ExceededIpcMaxSize
+    );
+}
+
+#[test]
+fn test_ipc_connect_not_socket() {
+    let path = std::env::temp_dir()
+        .join(format!("rabc_ipc_test_{}", std::process::id()));
+    std::fs::write(&path, "not a socket").unwrap();
+    // Daemon not running
+    let e = RabcConnection::connect_to(path.to_str().unwrap()).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::NotConnected);
+    let e = RabcConnection::connect_to(&format!("{}/sock", path.display()))
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::IpcConnectionError);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_ipc_connect_legacy_daemon() {
+    let path = std::env::temp_dir()
+        .join(format!("rabc_ipc_legacy_test_{}", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let listener = UnixListener::bind(&path).unwrap();
+    // Daemon of protocol version 1 replies "pong" in plain text
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = RabcConnection::new(stream).unwrap();
+        conn.ipc_recv().unwrap();
+        conn.ipc_send("pong").unwrap();
+    });
+    let e = RabcConnection::connect_to(path.to_str().unwrap()).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::ProtocolError);
+    handle.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{
+    daemon_requested_log_level, format_rfc3339, RabcLogRecord, RabcMessage,
+};
+
+#[test]
+fn test_format_rfc3339() {
+    assert_eq!(
+        format_rfc3339(
+            UNIX_EPOCH
+                + Duration::from_secs(1656662400)
+                + Duration::from_micros(123)
+        ),
+        "2022-07-01T08:00:00.000123Z"
+    );
+    assert_eq!(
+        format_rfc3339(UNIX_EPOCH + Duration::from_secs(951868799)),
+        "2000-02-29T23:59:59.000000Z"
+    );
+    assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000000Z");
+}
+
+#[test]
+fn test_log_record_message() {
+    let kvs = [("client_pid", 1234)];
+    let mut record = RabcLogRecord::from_record(
+        &log::Record::builder()
+            .args(format_args!("hello"))
+            .level(log::Level::Warn)
+            .target("rabcd")
+            .line(Some(10))
+            .key_values(&kvs)
+            .build(),
+        UNIX_EPOCH,
+    );
+    assert!(record.thread.is_some());
+    record.thread = None;
+    let message = RabcMessage::Log(record);
+    let json = message.to_json().unwrap();
+    assert_eq!(
+        json,
+        "{\"log\":{\"time\":\"1970-01-01T00:00:00.000000Z\",\
+         \"level\":\"WARN\",\"target\":\"rabcd\",\"msg\":\"hello\",\
+         \"line\":10,\"fields\":{\"client_pid\":\"1234\"}}}"
+    );
+    assert_eq!(RabcMessage::from_json(&json).unwrap(), message);
+    assert_eq!(
+        RabcMessage::from_json("{\"set_log_level\":\"DEBUG\"}").unwrap(),
+        RabcMessage::SetLogLevel(log::LevelFilter::Debug)
+    );
+}
+
+#[test]
+fn test_set_log_level_keep_max_level() {
+    let max_level = log::max_level();
+    assert_eq!(
+        RabcMessage::SetLogLevel(log::LevelFilter::Trace).apply_log(),
+        None
+    );
+    assert_eq!(daemon_requested_log_level(), Some(log::LevelFilter::Trace));
+    assert_eq!(log::max_level(), max_level);
+}
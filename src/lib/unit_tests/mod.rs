@@ -1,4 +1,12 @@
 // SPDX-License-Identifier: Apache-2.0
 
+#[cfg(test)]
+mod client;
+#[cfg(test)]
+mod error;
+#[cfg(test)]
+mod ipc;
+#[cfg(test)]
+mod logging;
 #[cfg(test)]
 mod timer;
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::os::unix::io::AsRawFd;
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rabc::{RabcClient, RabcClientMessage, RabcEvent, RabcReply};
+
+use rabc::{ErrorKind, RabcError};
+
+use crate::error::to_py_err;
+use crate::logger::log_level_from_py;
+
+// Python `logging.INFO`
+const PY_LOG_LEVEL_INFO: i32 = 20;
+
+/// Client wrapped by `rabc.RabcClient`, events are integers of
+/// `rabc.RabcEvent`.
+#[pyclass(name = "RabcClient", module = "rabc._rabc", subclass)]
+#[derive(Debug)]
+pub(crate) struct PyRabcClient {
+    // `None` after `close()`
+    client: Option<RabcClient>,
+}
+
+impl PyRabcClient {
+    fn client(&mut self) -> PyResult<&mut RabcClient> {
+        self.client.as_mut().ok_or_else(|| {
+            to_py_err(RabcError::new(
+                ErrorKind::NotConnected,
+                "RabcClient closed".to_string(),
+            ))
+        })
+    }
+}
+
+#[pymethods]
+impl PyRabcClient {
+    /// Connect to daemon listening on `socket_path`, default to
+    /// `/tmp/librabc`.
+    #[new]
+    #[pyo3(signature = (socket_path = None))]
+    fn new(py: Python<'_>, socket_path: Option<&str>) -> PyResult<Self> {
+        // Connecting waits for daemon to reply the protocol version
+        let client = py.allow_threads(|| match socket_path {
+            Some(path) => RabcClient::new_with_socket(path),
+            None => RabcClient::new(),
+        });
+        Ok(Self {
+            client: Some(client.map_err(to_py_err)?),
+        })
+    }
+
+    /// Disconnect from daemon, other methods raise `RabcNotConnectedError`
+    /// afterwards.
+    fn close(&mut self) {
+        self.client = None;
+    }
+
+    /// The fd which is readable when `poll()` has events to return, for
+    /// integrating with event loops.
+    fn fileno(&mut self) -> PyResult<i32> {
+        Ok(self.client()?.as_raw_fd())
+    }
+
+    /// Send request to daemon, the reply is returned by `process()` or
+    /// `process_message()`.
+    fn send(&mut self, request: &str) -> PyResult<()> {
+        self.client()?.send(request).map_err(to_py_err)
+    }
+
+    /// Wait up to `wait_time` seconds and return the list of events.
+    fn poll(&mut self, py: Python<'_>, wait_time: u32) -> PyResult<Vec<u64>> {
+        let client = self.client()?;
+        let events = py
+            .allow_threads(|| client.poll(wait_time))
+            .map_err(to_py_err)?;
+        Ok(events.into_iter().map(|e| e as u64).collect())
+    }
+
+    /// Process the event and return the reply string if any.
+    fn process(&mut self, event: u64) -> PyResult<Option<String>> {
+        let event = RabcEvent::try_from(event).map_err(to_py_err)?;
+        self.client()?.process(&event).map_err(to_py_err)
+    }
+
+    /// Process the event and return the message from daemon: `RabcRawReply`,
+    /// notification `dict` or `None`. Unlike `process()`, error replied by
+    /// daemon is stored in `RabcRawReply` instead of being raised.
+    fn process_message(
+        &mut self,
+        py: Python<'_>,
+        event: u64,
+    ) -> PyResult<PyObject> {
+        let event = RabcEvent::try_from(event).map_err(to_py_err)?;
+        match self.client()?.process_message(&event).map_err(to_py_err)? {
+            Some(RabcClientMessage::Reply(reply)) => {
+                Ok(PyRabcRawReply::from(reply).into_py(py))
+            }
+            Some(RabcClientMessage::Notification(notification)) => {
+                to_py_object(py, &notification)
+            }
+            _ => Ok(py.None()),
+        }
+    }
+
+    /// Same as `process()` but return the reply as `bytes` which might not
+    /// be valid UTF-8.
+    fn process_bytes<'py>(
+        &mut self,
+        py: Python<'py>,
+        event: u64,
+    ) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let event = RabcEvent::try_from(event).map_err(to_py_err)?;
+        Ok(self
+            .client()?
+            .process_bytes(&event)
+            .map_err(to_py_err)?
+            .map(|reply| PyBytes::new_bound(py, &reply)))
+    }
+
+    /// Ask daemon to stream its logs of specified Python logging level or
+    /// more severe, the logs are emitted to Python logging by later
+    /// `process()`. `None` stops the streaming.
+    #[pyo3(signature = (level = Some(PY_LOG_LEVEL_INFO)))]
+    fn request_daemon_logs(&mut self, level: Option<i32>) -> PyResult<()> {
+        self.client()?
+            .request_daemon_logs(log_level_from_py(level))
+            .map_err(to_py_err)
+    }
+}
+
+/// Reply from daemon paired with its request, decoded into
+/// `rabc.RabcReply` by `rabc.RabcClient`.
+#[pyclass(name = "RabcRawReply", module = "rabc._rabc", frozen)]
+#[derive(Debug)]
+pub(crate) struct PyRabcRawReply {
+    /// The request of this reply, `None` if daemon sent error without
+    /// request.
+    #[pyo3(get)]
+    request: Option<String>,
+    /// Whether the request is the heartbeat ping sent by the client
+    #[pyo3(get)]
+    heartbeat: bool,
+    /// Round-trip time of the request in seconds
+    #[pyo3(get)]
+    rtt: Option<f64>,
+    result: Result<Vec<u8>, PyErr>,
+}
+
+#[pymethods]
+impl PyRabcRawReply {
+    /// Return the reply string or raise the `RabcError` replied by daemon,
+    /// `RabcProtocolError` is raised if reply is not valid UTF-8.
+    fn result(&self, py: Python<'_>) -> PyResult<String> {
+        match &self.result {
+            Ok(reply) => String::from_utf8(reply.clone())
+                .map_err(|e| to_py_err(RabcError::from(e))),
+            Err(e) => Err(e.clone_ref(py)),
+        }
+    }
+
+    /// Same as `result()` but return the reply as `bytes`.
+    fn result_bytes<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        match &self.result {
+            Ok(reply) => Ok(PyBytes::new_bound(py, reply)),
+            Err(e) => Err(e.clone_ref(py)),
+        }
+    }
+}
+
+impl From<RabcReply> for PyRabcRawReply {
+    fn from(reply: RabcReply) -> Self {
+        Self {
+            request: reply.request,
+            heartbeat: reply.heartbeat,
+            rtt: reply.rtt.map(|rtt| rtt.as_secs_f64()),
+            result: reply.result.map_err(to_py_err),
+        }
+    }
+}
+
+// Convert to Python object via JSON
+fn to_py_object<T: serde::Serialize>(
+    py: Python<'_>,
+    value: &T,
+) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| to_py_err(RabcError::from(e)))?;
+    Ok(py
+        .import_bound("json")?
+        .call_method1("loads", (json,))?
+        .unbind())
+}
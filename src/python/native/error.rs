@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use rabc::RabcError;
+
+// `rabc.error._new_error()` cached by init()
+static NEW_ERROR: GILOnceCell<PyObject> = GILOnceCell::new();
+
+/// Cache the factory of `rabc.RabcError` subclasses on module init.
+pub(crate) fn init(py: Python<'_>) -> PyResult<()> {
+    let new_error = py.import_bound("rabc.error")?.getattr("_new_error")?;
+    NEW_ERROR.get_or_init(py, || new_error.unbind());
+    Ok(())
+}
+
+/// Convert to the `rabc.RabcError` subclass of the error kind, e.g.
+/// `rabc.RabcIpcConnectionError`. Plain `Exception` holding the kind and
+/// message is used if the subclass is not available.
+pub(crate) fn to_py_err(e: RabcError) -> PyErr {
+    Python::with_gil(|py| {
+        let kind = e.kind().to_string();
+        let msg = e.full_msg();
+        match NEW_ERROR.get(py).map(|f| f.call1(py, (&kind, &msg))) {
+            Some(Ok(err)) => PyErr::from_value_bound(err.into_bound(py)),
+            _ => PyException::new_err((kind, msg)),
+        }
+    })
+}
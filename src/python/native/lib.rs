@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// The code generated by pyo3 for `PyResult` return triggers this lint
+#[allow(clippy::useless_conversion)]
+mod client;
+mod error;
+mod logger;
+
+use pyo3::prelude::*;
+
+use crate::client::{PyRabcClient, PyRabcRawReply};
+
+/// Native module wrapped by the `rabc` Python package.
+#[pymodule]
+fn _rabc(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    logger::install();
+    // Errors are raised as plain `Exception` if `rabc.error` is missing
+    if error::init(m.py()).is_err() {
+        log::warn!(target: "rabc::python", "Failed to import rabc.error");
+    }
+    // Checked against the package version by `rabc/__init__.py`
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_class::<PyRabcClient>()?;
+    m.add_class::<PyRabcRawReply>()?;
+    Ok(())
+}
@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+// Python logging level of TRACE which is below DEBUG
+const PY_LOG_LEVEL_TRACE: u8 = 5;
+
+/// Forward the records of `rabc::` targets to Python `logging`, the logger
+/// is named after the target, e.g. `rabc.client` for `rabc::client`.
+/// Records above the level requested by daemon are discarded.
+struct PyLogger;
+
+impl log::Log for PyLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target().starts_with("rabc::")
+            && rabc::daemon_requested_log_level()
+                .is_none_or(|level| metadata.level() <= level)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        Python::with_gil(|py| {
+            if let Err(e) = emit(py, record) {
+                e.write_unraisable_bound(py, None);
+            }
+        })
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: PyLogger = PyLogger;
+
+/// Install the logger, Python `logging` decides which records to emit.
+pub(crate) fn install() {
+    // Fails when module is imported again by sub-interpreter
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+}
+
+/// Python `logging` level of specified level.
+fn py_log_level(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 40,
+        log::Level::Warn => 30,
+        log::Level::Info => 20,
+        log::Level::Debug => 10,
+        log::Level::Trace => PY_LOG_LEVEL_TRACE,
+    }
+}
+
+/// The `log::LevelFilter` of specified Python `logging` level, `None`
+/// means `Off`.
+pub(crate) fn log_level_from_py(level: Option<i32>) -> log::LevelFilter {
+    match level {
+        None => log::LevelFilter::Off,
+        Some(l) if l < 10 => log::LevelFilter::Trace,
+        Some(l) if l <= 10 => log::LevelFilter::Debug,
+        Some(l) if l <= 20 => log::LevelFilter::Info,
+        Some(l) if l <= 30 => log::LevelFilter::Warn,
+        Some(_) => log::LevelFilter::Error,
+    }
+}
+
+// Emit the record as `logging.LogRecord` keeping the Rust file and line.
+// The module, thread and key-value fields are stored as `rabc_module`,
+// `rabc_thread` and `rabc_fields` attributes.
+fn emit(py: Python<'_>, record: &log::Record) -> PyResult<()> {
+    let logger = py
+        .import_bound("logging")?
+        .call_method1("getLogger", (record.target().replace("::", "."),))?;
+    let level = py_log_level(record.level());
+    if !logger.call_method1("isEnabledFor", (level,))?.is_truthy()? {
+        return Ok(());
+    }
+    let extra = PyDict::new_bound(py);
+    extra.set_item("rabc_module", record.module_path())?;
+    extra.set_item("rabc_thread", current_thread_name())?;
+    extra.set_item("rabc_fields", fields(record))?;
+    let py_record = logger.call_method1(
+        "makeRecord",
+        (
+            logger.getattr("name")?,
+            level,
+            record.file().unwrap_or_default(),
+            record.line().unwrap_or_default(),
+            record.args().to_string(),
+            py.None(),
+            py.None(),
+            py.None(),
+            extra,
+        ),
+    )?;
+    logger.call_method1("handle", (py_record,))?;
+    Ok(())
+}
+
+fn fields(record: &log::Record) -> BTreeMap<String, String> {
+    struct Collector(BTreeMap<String, String>);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Collector {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            self.0.insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+    }
+
+    let mut collector = Collector(BTreeMap::new());
+    record.key_values().visit(&mut collector).ok();
+    collector.0
+}
+
+fn current_thread_name() -> String {
+    let thread = std::thread::current();
+    match thread.name() {
+        Some(name) => name.to_string(),
+        None => format!("{:?}", thread.id()),
+    }
+}
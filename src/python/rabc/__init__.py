@@ -1,6 +1,61 @@
 # SPDX-License-Identifier: Apache-2.0
 
-from .clib_wrapper import RabcError
-from .clib_wrapper import RabcClient
+import os
+
+# pylint: disable=no-name-in-module
+from ._rabc import __version__ as _native_version
+
+
+def _package_version():
+    """
+    Version of the installed `rabc` distribution which maturin takes from
+    native/Cargo.toml, `None` if this package is not installed, e.g. source
+    tree.
+    """
+    try:
+        # pylint: disable=import-outside-toplevel
+        from importlib import metadata
+
+        dist = metadata.distribution("rabc")
+    except ImportError:
+        return None
+    # Distribution installed elsewhere is not this package
+    init_file = dist.locate_file(os.path.join("rabc", "__init__.py"))
+    if os.path.realpath(init_file) != os.path.realpath(__file__):
+        return None
+    return dist.version
+
+
+__version__ = _package_version() or _native_version
+
+# A stale native module might not work with the Python code of this package
+if _native_version != __version__:
+    raise ImportError(
+        f"rabc native module version {_native_version} does not match "
+        f"rabc package version {__version__}, please reinstall rabc"
+    )
+
+# pylint: disable=wrong-import-position
+from .client import RabcClient
+from .client import RabcEvent
+from .error import ErrorKind
+from .error import RabcBugError
+from .error import RabcConnectionError
+from .error import RabcError
+from .error import RabcExceededIpcMaxSizeError
+from .error import RabcInvalidArgumentError
+from .error import RabcIpcConnectionError
+from .error import RabcNotConnectedError
+from .error import RabcPeerClosedError
+from .error import RabcPermissionDeniedError
+from .error import RabcProtocolError
+from .error import RabcThrottledError
+from .error import RabcTimeoutError
+from .message import RabcClientConnected
+from .message import RabcClientDisconnected
+from .message import RabcClientInfo
+from .message import RabcNotification
+from .message import RabcReply
+from .message import RabcStatus
 
 __all__ = []
@@ -0,0 +1,28 @@
+# SPDX-License-Identifier: Apache-2.0
+
+from typing import Any, Dict, List, Optional, Union
+
+__version__: str
+
+class RabcRawReply:
+    @property
+    def request(self) -> Optional[str]: ...
+    @property
+    def heartbeat(self) -> bool: ...
+    @property
+    def rtt(self) -> Optional[float]: ...
+    def result(self) -> str: ...
+    def result_bytes(self) -> bytes: ...
+
+class RabcClient:
+    def __init__(self, socket_path: Optional[str] = ...) -> None: ...
+    def close(self) -> None: ...
+    def fileno(self) -> int: ...
+    def send(self, request: str) -> None: ...
+    def poll(self, wait_time: int) -> List[int]: ...
+    def process(self, event: int) -> Optional[str]: ...
+    def process_message(
+        self, event: int
+    ) -> Union[RabcRawReply, Dict[str, Any], None]: ...
+    def process_bytes(self, event: int) -> Optional[bytes]: ...
+    def request_daemon_logs(self, level: Optional[int] = ...) -> None: ...
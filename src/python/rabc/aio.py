@@ -0,0 +1,154 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import asyncio
+import collections
+
+from .client import RabcClient as _RabcClient
+from .error import RabcError
+from .error import RabcNotConnectedError
+from .message import RabcReply
+
+_SUBSCRIBE_REQUEST = "subscribe"
+
+# Marks the end of notifications in queue
+_CLOSED = object()
+
+
+class RabcClient:
+    """
+    Client for asyncio which processes the events of `rabc.RabcClient`
+    when its fd is readable in the running event loop:
+
+        async with rabc.aio.RabcClient() as client:
+            print(await client.request("status"))
+            async for notification in client.notifications():
+                print(notification)
+
+    Any error on processing events closes the client and is raised by
+    pending `request()` and `notifications()`. The `socket_path` is the
+    UNIX socket daemon listening on, `None` means `/tmp/librabc`.
+    """
+
+    def __init__(self, socket_path=None):
+        self._socket_path = socket_path
+        self._client = None
+        self._loop = None
+        # Futures of requests in the order of sending
+        self._pending = collections.deque()
+        self._notifications = None
+        self._subscribed = False
+        # Error closed the client, `None` if closed by `close()`
+        self._error = None
+
+    async def connect(self):
+        if self._client is not None:
+            return
+        self._loop = asyncio.get_running_loop()
+        # Connecting blocks until daemon replied the protocol version
+        client = await self._loop.run_in_executor(
+            None, _RabcClient, self._socket_path
+        )
+        if self._client is not None:
+            # Connected by concurrent call
+            client.close()
+            return
+        self._client = client
+        self._notifications = asyncio.Queue()
+        self._error = None
+        self._loop.add_reader(self._client.fileno(), self._on_readable)
+
+    def close(self):
+        self._close(None)
+
+    async def __aenter__(self):
+        await self.connect()
+        return self
+
+    async def __aexit__(self, exc_type, exc_value, traceback):
+        self.close()
+
+    async def request(self, request):
+        """
+        Send request to daemon and return its reply decoded as
+        `RabcReply.reply`, the error replied by daemon is raised.
+        """
+        return (await self._request(request)).result()
+
+    async def request_bytes(self, request):
+        """
+        Same as `request()` but return the undecoded reply as `bytes`, see
+        `RabcReply.result_bytes()`.
+        """
+        return (await self._request(request)).result_bytes()
+
+    async def _request(self, request):
+        self._check_connected()
+        future = self._loop.create_future()
+        self._client.send(request)
+        self._pending.append(future)
+        return await future
+
+    async def notifications(self):
+        """
+        Subscribe to daemon notifications and iterate them as
+        `RabcNotification` until the client is closed.
+        """
+        if not self._subscribed:
+            await self.request(_SUBSCRIBE_REQUEST)
+            self._subscribed = True
+        notifications = self._notifications
+        while True:
+            notification = await notifications.get()
+            if notification is _CLOSED:
+                # Wake up other iterators
+                notifications.put_nowait(_CLOSED)
+                if self._error is not None:
+                    raise self._error
+                return
+            yield notification
+
+    def _check_connected(self):
+        if self._client is None:
+            raise self._error or _not_connected_error()
+
+    def _on_readable(self):
+        try:
+            for event in self._client.poll(0):
+                if self._client is None:
+                    return
+                self._dispatch(self._client.process_message(event))
+        except RabcError as e:
+            self._close(e)
+
+    def _dispatch(self, message):
+        if isinstance(message, RabcReply):
+            if message.heartbeat:
+                return
+            if message.request is None:
+                # Daemon sent error without request
+                message.result()
+            if self._pending:
+                future = self._pending.popleft()
+                # The request might be cancelled
+                if not future.done():
+                    future.set_result(message)
+        elif message is not None:
+            self._notifications.put_nowait(message)
+
+    def _close(self, error):
+        if self._client is None:
+            return
+        self._loop.remove_reader(self._client.fileno())
+        self._client.close()
+        self._client = None
+        self._subscribed = False
+        self._error = error
+        while self._pending:
+            future = self._pending.popleft()
+            if not future.done():
+                future.set_exception(error or _not_connected_error())
+        self._notifications.put_nowait(_CLOSED)
+
+
+def _not_connected_error():
+    return RabcNotConnectedError("RabcClient not connected")
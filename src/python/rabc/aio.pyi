@@ -0,0 +1,21 @@
+# SPDX-License-Identifier: Apache-2.0
+
+from types import TracebackType
+from typing import Any, AsyncIterator, Optional, Type
+
+from .message import RabcNotification
+
+class RabcClient:
+    def __init__(self, socket_path: Optional[str] = None) -> None: ...
+    async def connect(self) -> None: ...
+    def close(self) -> None: ...
+    async def __aenter__(self) -> "RabcClient": ...
+    async def __aexit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> None: ...
+    async def request(self, request: str) -> Any: ...
+    async def request_bytes(self, request: str) -> bytes: ...
+    def notifications(self) -> AsyncIterator[RabcNotification]: ...
@@ -0,0 +1,50 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import enum
+
+# pylint: disable=no-name-in-module
+from ._rabc import RabcClient as _RabcClient
+from .message import RabcNotification
+from .message import RabcReply
+
+
+class RabcEvent(enum.IntEnum):
+    """Event returned by `RabcClient.poll()`."""
+
+    IPC_IN = 1
+    TIMER = 2
+
+
+class RabcClient(_RabcClient):
+    """
+    Client connected to daemon on creation, use `close()` or `with`
+    statement to disconnect:
+
+        with RabcClient() as client:
+            client.send("status")
+            ...
+    """
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, exc_type, exc_value, traceback):
+        self.close()
+
+    def poll(self, wait_time):
+        """Wait up to `wait_time` seconds and return list of `RabcEvent`."""
+        return [RabcEvent(event) for event in super().poll(wait_time)]
+
+    def process_message(self, event):
+        """
+        Process the event and return the message from daemon:
+        `RabcReply`, `RabcNotification` or `None`. Unlike `process()`,
+        error replied by daemon is stored in `RabcReply` instead of being
+        raised.
+        """
+        message = super().process_message(event)
+        if message is None:
+            return None
+        if isinstance(message, dict):
+            return RabcNotification.from_dict(message)
+        return RabcReply.from_raw(message)
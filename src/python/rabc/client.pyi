@@ -0,0 +1,26 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import enum
+from types import TracebackType
+from typing import List, Optional, Type, Union
+
+from ._rabc import RabcClient as _RabcClient
+from .message import RabcNotification
+from .message import RabcReply
+
+class RabcEvent(enum.IntEnum):
+    IPC_IN = 1
+    TIMER = 2
+
+class RabcClient(_RabcClient):
+    def __enter__(self) -> "RabcClient": ...
+    def __exit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> None: ...
+    def poll(self, wait_time: int) -> List[RabcEvent]: ...  # type: ignore[override]
+    def process_message(  # type: ignore[override]
+        self, event: int
+    ) -> Union[RabcReply, RabcNotification, None]: ...
@@ -0,0 +1,116 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import enum
+
+
+class ErrorKind(str, enum.Enum):
+    """
+    Kind of `RabcError`, the value is the kind name used by the Rust and C
+    API, e.g. `IpcConnectionError`.
+    """
+
+    IPC_CONNECTION_ERROR = "IpcConnectionError"
+    EXCEEDED_IPC_MAX_SIZE = "ExceededIpcMaxSize"
+    INVALID_ARGUMENT = "InvalidArgument"
+    BUG = "Bug"
+    THROTTLED = "Throttled"
+    PERMISSION_DENIED = "PermissionDenied"
+    TIMEOUT = "Timeout"
+    NOT_CONNECTED = "NotConnected"
+    PROTOCOL_ERROR = "ProtocolError"
+    PEER_CLOSED = "PeerClosed"
+
+    def __str__(self):
+        return self.value
+
+
+class RabcError(Exception):
+    """
+    Base of the errors raised by rabc, each `ErrorKind` has its own subclass,
+    e.g. `RabcIpcConnectionError`.
+    """
+
+    def __init__(self, kind, msg):
+        self.kind = ErrorKind(kind)
+        self.msg = msg
+        super().__init__(f"{self.kind}: {msg}")
+
+    @property
+    def is_connection_error(self):
+        """Whether the connection is not usable any more."""
+        return self.kind in (
+            ErrorKind.IPC_CONNECTION_ERROR,
+            ErrorKind.NOT_CONNECTED,
+            ErrorKind.PEER_CLOSED,
+        )
+
+
+class RabcConnectionError(RabcError):
+    """
+    Base of the errors meaning the connection to daemon is not usable any
+    more: `RabcIpcConnectionError`, `RabcNotConnectedError` and
+    `RabcPeerClosedError`.
+    """
+
+
+class _KindError(RabcError):
+    KIND = None
+
+    def __init__(self, msg):
+        super().__init__(self.KIND, msg)
+
+
+class RabcIpcConnectionError(_KindError, RabcConnectionError):
+    KIND = ErrorKind.IPC_CONNECTION_ERROR
+
+
+class RabcExceededIpcMaxSizeError(_KindError):
+    KIND = ErrorKind.EXCEEDED_IPC_MAX_SIZE
+
+
+class RabcInvalidArgumentError(_KindError):
+    KIND = ErrorKind.INVALID_ARGUMENT
+
+
+class RabcBugError(_KindError):
+    KIND = ErrorKind.BUG
+
+
+class RabcThrottledError(_KindError):
+    KIND = ErrorKind.THROTTLED
+
+
+class RabcPermissionDeniedError(_KindError):
+    KIND = ErrorKind.PERMISSION_DENIED
+
+
+class RabcTimeoutError(_KindError):
+    KIND = ErrorKind.TIMEOUT
+
+
+class RabcNotConnectedError(_KindError, RabcConnectionError):
+    KIND = ErrorKind.NOT_CONNECTED
+
+
+class RabcProtocolError(_KindError):
+    KIND = ErrorKind.PROTOCOL_ERROR
+
+
+class RabcPeerClosedError(_KindError, RabcConnectionError):
+    KIND = ErrorKind.PEER_CLOSED
+
+
+_ERROR_TYPES = {cls.KIND: cls for cls in _KindError.__subclasses__()}
+
+
+def _new_error(kind, msg):
+    """
+    Create the `RabcError` subclass of specified kind name, used by the
+    native module. Kind unknown to this package is treated as `Bug`.
+    """
+    try:
+        kind = ErrorKind(kind)
+    except ValueError:
+        msg = f"{kind}: {msg}"
+        kind = ErrorKind.BUG
+    return _ERROR_TYPES[kind](msg)
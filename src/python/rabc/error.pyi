@@ -0,0 +1,40 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import enum
+from typing import Union
+
+class ErrorKind(str, enum.Enum):
+    IPC_CONNECTION_ERROR = "IpcConnectionError"
+    EXCEEDED_IPC_MAX_SIZE = "ExceededIpcMaxSize"
+    INVALID_ARGUMENT = "InvalidArgument"
+    BUG = "Bug"
+    THROTTLED = "Throttled"
+    PERMISSION_DENIED = "PermissionDenied"
+    TIMEOUT = "Timeout"
+    NOT_CONNECTED = "NotConnected"
+    PROTOCOL_ERROR = "ProtocolError"
+    PEER_CLOSED = "PeerClosed"
+
+class RabcError(Exception):
+    kind: ErrorKind
+    msg: str
+    def __init__(self, kind: Union[ErrorKind, str], msg: str) -> None: ...
+    @property
+    def is_connection_error(self) -> bool: ...
+
+class RabcConnectionError(RabcError): ...
+
+class _KindError(RabcError):
+    KIND: ErrorKind
+    def __init__(self, msg: str) -> None: ...
+
+class RabcIpcConnectionError(_KindError, RabcConnectionError): ...
+class RabcExceededIpcMaxSizeError(_KindError): ...
+class RabcInvalidArgumentError(_KindError): ...
+class RabcBugError(_KindError): ...
+class RabcThrottledError(_KindError): ...
+class RabcPermissionDeniedError(_KindError): ...
+class RabcTimeoutError(_KindError): ...
+class RabcNotConnectedError(_KindError, RabcConnectionError): ...
+class RabcProtocolError(_KindError): ...
+class RabcPeerClosedError(_KindError, RabcConnectionError): ...
@@ -0,0 +1,167 @@
+# SPDX-License-Identifier: Apache-2.0
+
+import dataclasses
+import json
+from typing import Any, Dict, List, Optional
+
+from .error import RabcError
+from .error import RabcProtocolError
+
+
+def _from_dict(cls, data):
+    # Ignore the fields unknown to this package
+    return cls(
+        **{
+            field.name: data.get(field.name)
+            for field in dataclasses.fields(cls)
+        }
+    )
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcClientInfo:
+    """Information of a client connected to daemon."""
+
+    id: int
+    pid: Optional[int]
+    uid: int
+    connect_time: int
+    messages_received: int
+    messages_sent: int
+    bytes_received: int
+    bytes_sent: int
+
+    @classmethod
+    def from_dict(cls, data):
+        return _from_dict(cls, data)
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcStatus:
+    """Reply of the `status` request."""
+
+    version: str
+    uptime: int
+    connection_count: int
+    max_connections: int
+    max_connections_per_uid: int
+    connections_per_uid: Dict[int, int]
+    rejected_connection_count: int
+    throttled_message_count: int
+    clients: List[RabcClientInfo]
+
+    @classmethod
+    def from_dict(cls, data):
+        status = _from_dict(cls, data)
+        return dataclasses.replace(
+            status,
+            # JSON object keys are strings
+            connections_per_uid={
+                int(uid): count
+                for uid, count in (status.connections_per_uid or {}).items()
+            },
+            clients=[
+                RabcClientInfo.from_dict(c) for c in status.clients or []
+            ],
+        )
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcNotification:
+    """
+    Event notified to subscribed clients, `RabcClientConnected` and
+    `RabcClientDisconnected` for known events.
+    """
+
+    event: str
+
+    @staticmethod
+    def from_dict(data):
+        event = data.get("event")
+        if event == "client_connected":
+            return RabcClientConnected(
+                event, RabcClientInfo.from_dict(data.get("client", {}))
+            )
+        if event == "client_disconnected":
+            return RabcClientDisconnected(event, data.get("id"))
+        return RabcNotification(event)
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcClientConnected(RabcNotification):
+    client: RabcClientInfo
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcClientDisconnected(RabcNotification):
+    id: int
+
+
+# Decoders of the JSON replies indexed by command
+_REPLY_DECODERS = {
+    "status": RabcStatus.from_dict,
+    "commands": list,
+}
+
+
+@dataclasses.dataclass(frozen=True)
+class RabcReply:
+    """
+    Reply from daemon paired with its request. The `reply` is decoded by
+    request, e.g. `RabcStatus` for `status`, otherwise kept as string.
+    Either `reply` or `error` is `None`.
+    """
+
+    # `None` if daemon sent error without request
+    request: Optional[str]
+    # Whether the request is the heartbeat ping sent by the client
+    heartbeat: bool
+    # Round-trip time of the request in seconds
+    rtt: Optional[float]
+    reply: Any
+    error: Optional[RabcError]
+    # The undecoded reply, `None` if daemon replied error
+    reply_bytes: Optional[bytes] = dataclasses.field(default=None, repr=False)
+
+    def result(self):
+        """Return the reply or raise the error replied by daemon."""
+        if self.error is not None:
+            raise self.error
+        return self.reply
+
+    def result_bytes(self):
+        """
+        Return the undecoded reply as `bytes` or raise the error replied by
+        daemon. Unlike `result()`, reply which is not valid UTF-8 is
+        returned instead of raising `RabcProtocolError`.
+        """
+        if self.reply_bytes is None:
+            raise self.error
+        return self.reply_bytes
+
+    @classmethod
+    def from_raw(cls, raw):
+        reply = None
+        reply_bytes = None
+        error = None
+        try:
+            reply_bytes = raw.result_bytes()
+            reply = _decode_reply(raw.request, raw.result())
+        except RabcError as e:
+            error = e
+        return cls(
+            raw.request, raw.heartbeat, raw.rtt, reply, error, reply_bytes
+        )
+
+
+def _decode_reply(request, reply):
+    command = (request or "").split(" ", 1)[0]
+    decoder = _REPLY_DECODERS.get(command)
+    if decoder is None:
+        return reply
+    try:
+        return decoder(json.loads(reply))
+    except (ValueError, TypeError, AttributeError) as e:
+        raise RabcProtocolError(
+            f"Invalid reply {reply!r} of request {request!r}: {e}"
+        ) from e
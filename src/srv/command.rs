@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::{ErrorKind, RabcError};
+
+/// Requests supported by daemon, the first word of the request is the
+/// command name, the remaining are arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RabcdCommand {
+    Ping,
+    Status,
+    Version,
+    Subscribe,
+    Unsubscribe,
+    Commands,
+    /// `logs <level>`: stream daemon logs up to specified level
+    Logs,
+    /// `client_log_level <id> <level>`: change max log level of client
+    ClientLogLevel,
+    /// `protocol <version>`: state the protocol version of client, replied
+    /// with the version daemon will use
+    Protocol,
+}
+
+impl RabcdCommand {
+    pub(crate) const ALL: [Self; 9] = [
+        Self::Ping,
+        Self::Status,
+        Self::Version,
+        Self::Subscribe,
+        Self::Unsubscribe,
+        Self::Commands,
+        Self::Logs,
+        Self::ClientLogLevel,
+        Self::Protocol,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Ping => "ping",
+            Self::Status => "status",
+            Self::Version => "version",
+            Self::Subscribe => "subscribe",
+            Self::Unsubscribe => "unsubscribe",
+            Self::Commands => "commands",
+            Self::Logs => "logs",
+            Self::ClientLogLevel => "client_log_level",
+            Self::Protocol => "protocol",
+        }
+    }
+
+    pub(crate) fn parse(request: &str) -> Result<Self, RabcError> {
+        let name = request.split_whitespace().next().unwrap_or_default();
+        Self::ALL
+            .iter()
+            .find(|c| c.name() == name)
+            .copied()
+            .ok_or_else(|| {
+                RabcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Unknown command '{}'", name),
+                )
+                .with_details(format!(
+                    "Supported commands: {}",
+                    Self::ALL
+                        .iter()
+                        .map(|c| c.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// Parse log level argument: off, error, warn, info, debug or trace.
+pub(crate) fn parse_log_level(
+    arg: Option<&str>,
+) -> Result<log::LevelFilter, RabcError> {
+    let arg = arg.unwrap_or_default();
+    arg.parse().map_err(|_| {
+        RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid log level '{}'", arg),
+        )
+        .with_details(
+            "Supported levels: off, error, warn, info, debug, trace"
+                .to_string(),
+        )
+    })
+}
+
+/// Parse client ID argument.
+pub(crate) fn parse_client_id(arg: Option<&str>) -> Result<u64, RabcError> {
+    let arg = arg.unwrap_or_default();
+    arg.parse().map_err(|_| {
+        RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid client ID '{}'", arg),
+        )
+    })
+}
+
+/// Parse protocol version argument, the JSON protocol starts from version 2.
+pub(crate) fn parse_protocol_version(
+    arg: Option<&str>,
+) -> Result<u32, RabcError> {
+    let arg = arg.unwrap_or_default();
+    match arg.parse() {
+        Ok(version) if version >= 2 => Ok(version),
+        _ => Err(RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Unsupported protocol version '{}'", arg),
+        )),
+    }
+}
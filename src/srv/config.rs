@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::path::Path;
+
+use rabc::{ErrorKind, RabcError};
+use serde::Deserialize;
+
+use crate::logger::LogBackend;
+
+/// Daemon configuration file in YAML, command line options take precedence
+/// over it. Example:
+///
+/// ```yaml
+/// log:
+///   backend: journald
+///   filter: info,rabcd::limits=debug
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RabcdConfig {
+    #[serde(default)]
+    pub(crate) log: RabcdLogConfig,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RabcdLogConfig {
+    pub(crate) backend: Option<LogBackend>,
+    /// Same syntax as `RUST_LOG`
+    pub(crate) filter: Option<String>,
+}
+
+impl RabcdConfig {
+    pub(crate) fn load(path: &Path) -> Result<Self, RabcError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            RabcError::new(
+                ErrorKind::InvalidArgument,
+                format!("Failed to read config {}: {}", path.display(), e),
+            )
+        })?;
+        Self::from_yaml(&content).map_err(|e| {
+            RabcError::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid config {}: {}", path.display(), e.msg()),
+            )
+        })
+    }
+
+    pub(crate) fn from_yaml(content: &str) -> Result<Self, RabcError> {
+        serde_yaml::from_str(content).map_err(|e| {
+            RabcError::new(ErrorKind::InvalidArgument, e.to_string())
+        })
+    }
+}
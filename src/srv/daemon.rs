@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg, OFlag};
+use nix::sys::stat::{umask, Mode};
+use nix::unistd::{
+    chdir, close, dup2, fork, getpid, pipe, read, setsid, write, ForkResult,
+};
+use rabc::{ErrorKind, RabcError};
+
+const LOCK_FILE_SUFFIX: &str = ".lock";
+const DEV_NULL: &str = "/dev/null";
+
+/// Whether rabcd is started by systemd as a service, systemd expects
+/// services to stay in foreground by default.
+pub(crate) fn is_under_systemd() -> bool {
+    std::env::var_os("INVOCATION_ID").is_some()
+        || std::env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+/// Path of the lock file guarding specified socket path.
+pub(crate) fn lock_file_path(socket: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", socket, LOCK_FILE_SUFFIX))
+}
+
+/// Exclusive lock preventing multiple rabcd instances serving the same
+/// socket. The lock is released when the process exits, the lock file holds
+/// the PID of the lock owner.
+#[derive(Debug)]
+pub(crate) struct InstanceLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    pub(crate) fn acquire(path: &Path) -> Result<Self, RabcError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o644)
+            .custom_flags(OFlag::O_NOFOLLOW.bits())
+            .open(path)
+            .map_err(|e| {
+                RabcError::new(
+                    ErrorKind::Bug,
+                    format!(
+                        "Failed to open lock file {}: {}",
+                        path.display(),
+                        e
+                    ),
+                )
+            })?;
+        match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => Ok(Self {
+                file,
+                path: path.to_path_buf(),
+            }),
+            Err(Errno::EWOULDBLOCK) => {
+                let mut pid = String::new();
+                file.read_to_string(&mut pid).ok();
+                Err(RabcError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "Another rabcd instance (pid {}) is holding lock \
+                         file {}",
+                        match pid.trim() {
+                            "" => "unknown",
+                            p => p,
+                        },
+                        path.display()
+                    ),
+                ))
+            }
+            Err(e) => Err(RabcError::new(
+                ErrorKind::Bug,
+                format!("Failed to lock {}: {}", path.display(), e),
+            )),
+        }
+    }
+
+    /// Store PID of current process into lock file, should be invoked after
+    /// [daemonize()].
+    pub(crate) fn write_pid(&mut self) -> Result<(), RabcError> {
+        self.file
+            .set_len(0)
+            .and_then(|_| self.file.seek(SeekFrom::Start(0)))
+            .and_then(|_| writeln!(self.file, "{}", getpid()))
+            .map_err(|e| {
+                RabcError::new(
+                    ErrorKind::Bug,
+                    format!(
+                        "Failed to write PID to lock file {}: {}",
+                        self.path.display(),
+                        e
+                    ),
+                )
+            })
+    }
+}
+
+/// Write PID of current process to specified file, symbolic link is
+/// refused.
+pub(crate) fn write_pid_file(path: &Path) -> Result<(), RabcError> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o644)
+        .custom_flags(OFlag::O_NOFOLLOW.bits())
+        .open(path)
+        .and_then(|mut f| writeln!(f, "{}", getpid()))
+        .map_err(|e| {
+            RabcError::new(
+                ErrorKind::Bug,
+                format!("Failed to write PID file {}: {}", path.display(), e),
+            )
+        })
+}
+
+/// Remove the socket left by previous run, refuse to remove other files.
+pub(crate) fn remove_stale_socket(path: &Path) -> Result<(), RabcError> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path).map_err(|e| {
+                RabcError::from(e).with_context(format!(
+                    "Failed to remove stale socket {}",
+                    path.display()
+                ))
+            })
+        }
+        Ok(_) => Err(RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Socket path {} exists and is not a socket",
+                path.display()
+            ),
+        )),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Socket file bound by current process, removed on drop unless it has
+/// been replaced by others.
+#[derive(Debug)]
+pub(crate) struct SocketFile {
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+impl SocketFile {
+    /// Should be invoked right after binding the socket.
+    pub(crate) fn new(path: &Path) -> Result<Self, RabcError> {
+        let metadata = std::fs::symlink_metadata(path).map_err(|e| {
+            RabcError::from(e).with_context(format!(
+                "Failed to query socket {}",
+                path.display()
+            ))
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Ok(metadata) = std::fs::symlink_metadata(&self.path) {
+            if metadata.dev() == self.dev && metadata.ino() == self.ino {
+                std::fs::remove_file(&self.path).ok();
+            }
+        }
+    }
+}
+
+/// Used by daemonized process to tell the original process that it is
+/// ready to serve clients.
+#[derive(Debug)]
+pub(crate) struct ReadyNotifier {
+    fd: RawFd,
+}
+
+impl ReadyNotifier {
+    /// Let the original process exit with 0 and detach from the terminal.
+    /// Until then, errors are still logged to the terminal.
+    pub(crate) fn ready(self) -> Result<(), RabcError> {
+        write(self.fd, b"1").map_err(|e| {
+            RabcError::new(
+                ErrorKind::Bug,
+                format!("Failed to notify parent process: {}", e),
+            )
+        })?;
+        close(self.fd).ok();
+        redirect_stdio_to_null()
+    }
+}
+
+/// Classic double-fork daemonize. The original process waits until
+/// [ReadyNotifier::ready()] is invoked by the daemon and exits with 0, or
+/// exits with 1 if the daemon quit before that.
+/// Must be invoked before any thread is created.
+pub(crate) fn daemonize() -> Result<ReadyNotifier, RabcError> {
+    let (read_fd, write_fd) = pipe().map_err(|e| fork_error("pipe", e))?;
+    // SAFETY: No thread has been created yet
+    match unsafe { fork() }.map_err(|e| fork_error("fork", e))? {
+        ForkResult::Parent { .. } => {
+            close(write_fd).ok();
+            let mut buf = [0u8; 1];
+            let code = match read(read_fd, &mut buf) {
+                Ok(1) => 0,
+                _ => 1,
+            };
+            std::process::exit(code);
+        }
+        ForkResult::Child => (),
+    }
+    close(read_fd).ok();
+    setsid().map_err(|e| fork_error("setsid", e))?;
+    // Fork again so the daemon is not session leader and cannot acquire
+    // controlling terminal
+    // SAFETY: No thread has been created yet
+    match unsafe { fork() }.map_err(|e| fork_error("fork", e))? {
+        ForkResult::Parent { .. } => std::process::exit(0),
+        ForkResult::Child => (),
+    }
+    chdir("/").map_err(|e| fork_error("chdir", e))?;
+    umask(Mode::from_bits_truncate(0o022));
+    Ok(ReadyNotifier { fd: write_fd })
+}
+
+fn redirect_stdio_to_null() -> Result<(), RabcError> {
+    let null = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(DEV_NULL)
+        .map_err(|e| {
+            RabcError::new(
+                ErrorKind::Bug,
+                format!("Failed to open {}: {}", DEV_NULL, e),
+            )
+        })?;
+    for fd in [
+        std::io::stdin().as_raw_fd(),
+        std::io::stdout().as_raw_fd(),
+        std::io::stderr().as_raw_fd(),
+    ] {
+        dup2(null.as_raw_fd(), fd).map_err(|e| fork_error("dup2", e))?;
+    }
+    Ok(())
+}
+
+fn fork_error(action: &str, e: Errno) -> RabcError {
+    RabcError::new(
+        ErrorKind::Bug,
+        format!("Failed to daemonize, {}() failed: {}", action, e),
+    )
+}
@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rabc::{ErrorKind, RabcError};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+
+use crate::daemon::remove_stale_socket;
+use crate::metrics::RabcdMetrics;
+
+// Requests are only used to trigger the scrape, drop anything bigger
+const MAX_REQUEST_SIZE: usize = 8192;
+// Idle connections are dropped after this
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Where to serve the metrics.
+#[derive(Debug, Clone)]
+pub(crate) enum MetricsListen {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+/// Bound listener serving the metrics.
+#[derive(Debug)]
+pub(crate) enum MetricsListener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl MetricsListener {
+    pub(crate) async fn bind(
+        listen: &MetricsListen,
+    ) -> Result<Self, RabcError> {
+        match listen {
+            MetricsListen::Unix(path) => {
+                remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path).map_err(|e| {
+                    RabcError::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Failed to bind metrics socket {}: {}",
+                            path.display(),
+                            e
+                        ),
+                    )
+                })?;
+                log::info!("Serving metrics on {}", path.display());
+                Ok(Self::Unix(listener))
+            }
+            MetricsListen::Tcp(address) => {
+                if !address.ip().is_loopback() {
+                    return Err(RabcError::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Metrics address {} is not a loopback address",
+                            address
+                        ),
+                    ));
+                }
+                let listener =
+                    TcpListener::bind(address).await.map_err(|e| {
+                        RabcError::new(
+                            ErrorKind::InvalidArgument,
+                            format!(
+                                "Failed to bind metrics address {}: {}",
+                                address, e
+                            ),
+                        )
+                    })?;
+                log::info!("Serving metrics on {}", address);
+                Ok(Self::Tcp(listener))
+            }
+        }
+    }
+
+    /// Serve metrics in OpenMetrics text format over HTTP.
+    pub(crate) async fn serve(self, metrics: Arc<RabcdMetrics>) {
+        loop {
+            let metrics = metrics.clone();
+            let result = match &self {
+                Self::Unix(listener) => {
+                    listener.accept().await.map(|(s, _)| {
+                        tokio::spawn(process_scrape(s, metrics));
+                    })
+                }
+                Self::Tcp(listener) => listener.accept().await.map(|(s, _)| {
+                    tokio::spawn(process_scrape(s, metrics));
+                }),
+            };
+            if let Err(e) = result {
+                log::error!("Failed to accept metrics connection {}", e);
+            }
+        }
+    }
+}
+
+async fn process_scrape<S>(mut stream: S, metrics: Arc<RabcdMetrics>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    match tokio::time::timeout(
+        REQUEST_TIMEOUT,
+        read_request_header(&mut stream),
+    )
+    .await
+    {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => {
+            log::debug!("Failed to read metrics request: {}", e);
+            return;
+        }
+        Err(_) => {
+            log::debug!(
+                "Timeout on reading metrics request after {:?}",
+                REQUEST_TIMEOUT
+            );
+            return;
+        }
+    }
+    let body = metrics.to_openmetrics();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        CONTENT_TYPE,
+        body.len(),
+        body
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        log::debug!("Failed to send metrics: {}", e);
+    }
+    stream.shutdown().await.ok();
+}
+
+// The request is not parsed, any request gets the metrics
+async fn read_request_header<S>(stream: &mut S) -> Result<(), RabcError>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !data.windows(4).any(|w| w == b"\r\n\r\n") {
+        let size = stream.read(&mut buffer).await?;
+        if size == 0 {
+            break;
+        }
+        data.extend_from_slice(&buffer[..size]);
+        if data.len() > MAX_REQUEST_SIZE {
+            return Err(RabcError::new(
+                ErrorKind::ExceededIpcMaxSize,
+                format!(
+                    "Metrics request exceeded the max size {} bytes",
+                    MAX_REQUEST_SIZE
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
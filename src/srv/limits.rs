@@ -0,0 +1,277 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use rabc::{ErrorKind, RabcError, RabcMessage, RabcNotification, RabcStatus};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::metrics::RabcdMetrics;
+
+/// Resource limits applied to clients, 0 means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct RabcdLimits {
+    pub(crate) max_connections: usize,
+    pub(crate) max_connections_per_uid: usize,
+    /// Messages allowed per second for each connection
+    pub(crate) max_message_rate: u32,
+    /// Messages allowed to be sent in a burst by each connection
+    pub(crate) max_message_burst: u32,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionCounter {
+    count: usize,
+    count_per_uid: HashMap<u32, usize>,
+    // Channel of each connection for messages initiated by daemon, indexed
+    // by connection ID
+    controls: HashMap<u64, (u32, mpsc::UnboundedSender<RabcMessage>)>,
+}
+
+// Notifications queued for each subscriber before it starts lagging
+const NOTIFICATION_QUEUE_SIZE: usize = 64;
+
+/// Track connections of all clients and enforce the connection limits.
+/// Connection changes are notified to subscribers.
+#[derive(Debug)]
+pub(crate) struct ConnectionTracker {
+    limits: RabcdLimits,
+    metrics: Arc<RabcdMetrics>,
+    counter: Mutex<ConnectionCounter>,
+    notifier: broadcast::Sender<RabcNotification>,
+}
+
+impl ConnectionTracker {
+    pub(crate) fn new(limits: RabcdLimits, metrics: Arc<RabcdMetrics>) -> Self {
+        let (notifier, _) = broadcast::channel(NOTIFICATION_QUEUE_SIZE);
+        Self {
+            limits,
+            metrics,
+            counter: Mutex::new(ConnectionCounter::default()),
+            notifier,
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<RabcNotification> {
+        self.notifier.subscribe()
+    }
+
+    fn notify(&self, notification: RabcNotification) {
+        // Error means no subscriber, which is OK
+        self.notifier.send(notification).ok();
+    }
+
+    pub(crate) fn limits(&self) -> &RabcdLimits {
+        &self.limits
+    }
+
+    pub(crate) fn metrics(&self) -> &RabcdMetrics {
+        &self.metrics
+    }
+
+    /// Register new connection from specified user ID.
+    /// The connection is unregistered when returned [ConnectionGuard] is
+    /// dropped.
+    pub(crate) fn connect(
+        self: &Arc<Self>,
+        uid: u32,
+        pid: Option<i32>,
+    ) -> Result<ConnectionGuard, RabcError> {
+        let mut counter = self.counter.lock().expect("inner lock poisoned");
+        if self.limits.max_connections != 0
+            && counter.count >= self.limits.max_connections
+        {
+            self.metrics.connection_rejected();
+            return Err(RabcError::new(
+                ErrorKind::Throttled,
+                format!(
+                    "Exceeded the max connection count {}",
+                    self.limits.max_connections
+                ),
+            ));
+        }
+        let uid_count = counter.count_per_uid.get(&uid).copied().unwrap_or(0);
+        if self.limits.max_connections_per_uid != 0
+            && uid_count >= self.limits.max_connections_per_uid
+        {
+            self.metrics.connection_rejected();
+            return Err(RabcError::new(
+                ErrorKind::Throttled,
+                format!(
+                    "Exceeded the max connection count {} for user ID {}",
+                    self.limits.max_connections_per_uid, uid
+                ),
+            ));
+        }
+        counter.count += 1;
+        counter.count_per_uid.insert(uid, uid_count + 1);
+        let info = self.metrics.client_connected(uid, pid);
+        let id = info.id;
+        let (sender, control) = mpsc::unbounded_channel();
+        counter.controls.insert(id, (uid, sender));
+        self.notify(RabcNotification::ClientConnected { client: info });
+        Ok(ConnectionGuard {
+            tracker: self.clone(),
+            id,
+            uid,
+            control,
+        })
+    }
+
+    /// Send message to the connection of specified ID on behalf of a client
+    /// of user ID `requester_uid`. Only root could send to connections of
+    /// other users.
+    pub(crate) fn send_to_client(
+        &self,
+        requester_uid: u32,
+        id: u64,
+        message: RabcMessage,
+    ) -> Result<(), RabcError> {
+        let counter = self.counter.lock().expect("inner lock poisoned");
+        let (uid, sender) = counter.controls.get(&id).ok_or_else(|| {
+            RabcError::new(
+                ErrorKind::InvalidArgument,
+                format!("Client {} not found", id),
+            )
+        })?;
+        if requester_uid != 0 && requester_uid != *uid {
+            return Err(RabcError::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Client {} belongs to another user, only root is \
+                     permitted",
+                    id
+                ),
+            ));
+        }
+        // Error means the connection is closing, which is OK
+        sender.send(message).ok();
+        Ok(())
+    }
+
+    pub(crate) fn status(&self) -> RabcStatus {
+        self.metrics.status(&self.limits)
+    }
+
+    /// Status requested by a client of user ID `requester_uid`. Clients of
+    /// other users are hidden unless requester is privileged.
+    pub(crate) fn status_for(&self, requester_uid: u32) -> RabcStatus {
+        let mut status = self.status();
+        if !is_privileged(requester_uid) {
+            status.clients.retain(|c| c.uid == requester_uid);
+            status
+                .connections_per_uid
+                .retain(|uid, _| *uid == requester_uid);
+        }
+        status
+    }
+
+    fn disconnect(&self, id: u64, uid: u32) {
+        self.metrics.client_disconnected(id);
+        self.notify(RabcNotification::ClientDisconnected { id });
+        let mut counter = self.counter.lock().expect("inner lock poisoned");
+        counter.count = counter.count.saturating_sub(1);
+        counter.controls.remove(&id);
+        if let Some(uid_count) = counter.count_per_uid.get_mut(&uid) {
+            *uid_count = uid_count.saturating_sub(1);
+            if *uid_count == 0 {
+                counter.count_per_uid.remove(&uid);
+            }
+        }
+    }
+}
+
+/// Whether user ID is root or the user running daemon, which are permitted
+/// to see the pid and uid of clients of other users.
+pub(crate) fn is_privileged(uid: u32) -> bool {
+    uid == 0 || uid == nix::unistd::getuid().as_raw()
+}
+
+/// Whether notification could be sent to subscriber of user ID `uid`, the
+/// connection of clients of other users are hidden unless privileged.
+pub(crate) fn is_notification_visible(
+    notification: &RabcNotification,
+    uid: u32,
+) -> bool {
+    match notification {
+        RabcNotification::ClientConnected { client } => {
+            client.uid == uid || is_privileged(uid)
+        }
+        _ => true,
+    }
+}
+
+/// Registered client connection, unregistered on drop.
+#[derive(Debug)]
+pub(crate) struct ConnectionGuard {
+    tracker: Arc<ConnectionTracker>,
+    id: u64,
+    uid: u32,
+    control: mpsc::UnboundedReceiver<RabcMessage>,
+}
+
+impl ConnectionGuard {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Wait for message sent by [ConnectionTracker::send_to_client()].
+    pub(crate) async fn recv_control(&mut self) -> Option<RabcMessage> {
+        self.control.recv().await
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.tracker.disconnect(self.id, self.uid);
+    }
+}
+
+/// Token bucket limiting the message rate of single connection.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Allow `rate` messages per second with burst up to `burst` messages.
+    /// A `rate` of 0 means unlimited.
+    pub(crate) fn new(rate: u32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate: f64::from(rate),
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take a token from the bucket, return false if the bucket is empty.
+    pub(crate) fn try_take(&mut self) -> bool {
+        self.try_take_at(Instant::now())
+    }
+
+    pub(crate) fn try_take_at(&mut self, now: Instant) -> bool {
+        if self.rate == 0.0 {
+            return true;
+        }
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate)
+            .min(self.capacity);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
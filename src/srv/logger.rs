@@ -0,0 +1,411 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::os::unix::net::UnixDatagram;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use clap::ValueEnum;
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use log::LevelFilter;
+use rabc::{format_rfc3339, ErrorKind, RabcError, RabcLogRecord};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+
+const DEFAULT_LOG_FILTER: &str = "rabc=info";
+const LOG_FILTER_ENV: &str = "RUST_LOG";
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+const SYSLOG_IDENTIFIER: &str = "rabcd";
+const SYSLOG_FACILITY_DAEMON: u8 = 3;
+// Private enterprise number reserved for documentation by RFC 5612, used as
+// structured data ID for the key-value fields.
+const SYSLOG_SD_ID: &str = "rabcd@32473";
+// Log records queued for each client before it starts lagging
+const LOG_QUEUE_SIZE: usize = 1024;
+
+static LOGGER: OnceLock<RabcdLogger> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogBackend {
+    /// Human readable lines to stderr
+    Stderr,
+    /// Native journald protocol with structured fields
+    Journald,
+    /// RFC 5424 syslog over `/dev/log`
+    Syslog,
+    /// JSON lines to stdout
+    Json,
+}
+
+#[derive(Debug)]
+enum Backend {
+    Stderr,
+    Journald(UnixDatagram),
+    Syslog(UnixDatagram),
+    Json,
+}
+
+#[derive(Debug)]
+struct RabcdLogger {
+    filter: Filter,
+    backend: Backend,
+    hostname: String,
+    // Daemon keeps logging to terminal until detached from it
+    copy_to_stderr: AtomicBool,
+    forwarder: LogForwarder,
+}
+
+/// Forward log records to clients requested them regardless of the log
+/// filter, each client filters the records by its own level.
+#[derive(Debug)]
+struct LogForwarder {
+    sender: broadcast::Sender<RabcLogRecord>,
+    // Requested level indexed by client ID
+    levels: Mutex<HashMap<u64, LevelFilter>>,
+    // Max of requested levels, stored as `LevelFilter as usize`
+    max_level: AtomicUsize,
+}
+
+impl LogForwarder {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(LOG_QUEUE_SIZE);
+        Self {
+            sender,
+            levels: Mutex::new(HashMap::new()),
+            max_level: AtomicUsize::new(LevelFilter::Off as usize),
+        }
+    }
+
+    fn enabled(&self, level: log::Level) -> bool {
+        level as usize <= self.max_level.load(Ordering::Relaxed)
+    }
+
+    // Update the level of client and return the max of requested levels
+    fn set_level(&self, id: u64, level: LevelFilter) -> LevelFilter {
+        let mut levels = self.levels.lock().expect("inner lock poisoned");
+        if level == LevelFilter::Off {
+            levels.remove(&id);
+        } else {
+            levels.insert(id, level);
+        }
+        let max_level =
+            levels.values().copied().max().unwrap_or(LevelFilter::Off);
+        self.max_level.store(max_level as usize, Ordering::Relaxed);
+        max_level
+    }
+}
+
+/// Install the global logger. The `filter` uses the `RUST_LOG` syntax, e.g.
+/// `info,rabcd::limits=debug`, `RUST_LOG` environment variable is used when
+/// not defined.
+pub(crate) fn init_logger(
+    backend: LogBackend,
+    filter: Option<&str>,
+    copy_to_stderr: bool,
+) -> Result<(), RabcError> {
+    let spec = match filter {
+        Some(f) => f.to_string(),
+        None => std::env::var(LOG_FILTER_ENV)
+            .unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_string()),
+    };
+    let filter = FilterBuilder::new().parse(&spec).build();
+    let max_level = filter.filter();
+    let (backend, fallback_error) = match Backend::new(backend) {
+        Ok(b) => (b, None),
+        Err(e) => (Backend::Stderr, Some(e)),
+    };
+    let copy_to_stderr = copy_to_stderr && !matches!(backend, Backend::Stderr);
+    let logger = LOGGER.get_or_init(|| RabcdLogger {
+        filter,
+        backend,
+        hostname: hostname(),
+        copy_to_stderr: AtomicBool::new(copy_to_stderr),
+        forwarder: LogForwarder::new(),
+    });
+    log::set_logger(logger).map_err(|e| {
+        RabcError::new(ErrorKind::Bug, format!("Failed to set logger: {}", e))
+    })?;
+    log::set_max_level(max_level);
+    if let Some(e) = fallback_error {
+        log::warn!("{}, logging to stderr instead", e);
+    }
+    Ok(())
+}
+
+/// Stop copying logs to stderr once daemon detached from terminal.
+pub(crate) fn stop_copy_to_stderr() {
+    if let Some(logger) = LOGGER.get() {
+        logger.copy_to_stderr.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Forward log records up to `level` to client of specified ID through the
+/// returned receiver, records above the level should be skipped by the
+/// client. [LevelFilter::Off] stops the forwarding and returns `None`.
+pub(crate) fn forward_logs(
+    id: u64,
+    level: LevelFilter,
+) -> Option<broadcast::Receiver<RabcLogRecord>> {
+    let logger = LOGGER.get()?;
+    let forward_level = logger.forwarder.set_level(id, level);
+    log::set_max_level(logger.filter.filter().max(forward_level));
+    if level == LevelFilter::Off {
+        None
+    } else {
+        Some(logger.forwarder.sender.subscribe())
+    }
+}
+
+impl Backend {
+    fn new(backend: LogBackend) -> Result<Self, RabcError> {
+        Ok(match backend {
+            LogBackend::Stderr => Self::Stderr,
+            LogBackend::Json => Self::Json,
+            LogBackend::Journald => {
+                let socket = UnixDatagram::unbound()?;
+                socket
+                    .connect(JOURNALD_SOCKET)
+                    .map_err(|e| socket_error(JOURNALD_SOCKET, e))?;
+                Self::Journald(socket)
+            }
+            LogBackend::Syslog => {
+                let socket = UnixDatagram::unbound()?;
+                socket
+                    .connect(SYSLOG_SOCKET)
+                    .map_err(|e| socket_error(SYSLOG_SOCKET, e))?;
+                Self::Syslog(socket)
+            }
+        })
+    }
+}
+
+fn socket_error(path: &str, e: std::io::Error) -> RabcError {
+    RabcError::from(e).with_context(format!("Failed to connect to {}", path))
+}
+
+impl log::Log for RabcdLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.filter.enabled(metadata)
+            || self.forwarder.enabled(metadata.level())
+    }
+
+    fn log(&self, record: &log::Record) {
+        let now = SystemTime::now();
+        if self.forwarder.enabled(record.level())
+            && self.forwarder.sender.receiver_count() > 0
+        {
+            // Error means no client is receiving, which is OK
+            self.forwarder
+                .sender
+                .send(RabcLogRecord::from_record(record, now))
+                .ok();
+        }
+        if !self.filter.matches(record) {
+            return;
+        }
+        // Logging failures cannot be logged, ignore them
+        match &self.backend {
+            Backend::Stderr => {
+                write_stderr(record, now);
+                return;
+            }
+            Backend::Json => {
+                let mut stdout = std::io::stdout().lock();
+                writeln!(stdout, "{}", json_line(record, now)).ok();
+            }
+            Backend::Journald(socket) => {
+                socket.send(&journald_payload(record)).ok();
+            }
+            Backend::Syslog(socket) => {
+                let msg = syslog_message(
+                    record,
+                    now,
+                    &self.hostname,
+                    std::process::id(),
+                );
+                socket.send(msg.as_bytes()).ok();
+            }
+        }
+        if self.copy_to_stderr.load(Ordering::Relaxed) {
+            write_stderr(record, now);
+        }
+    }
+
+    fn flush(&self) {
+        if let Backend::Json = self.backend {
+            std::io::stdout().flush().ok();
+        }
+    }
+}
+
+fn write_stderr(record: &log::Record, time: SystemTime) {
+    let mut line = format!(
+        "[{} {:<5} {}] {}",
+        format_rfc3339(time),
+        record.level(),
+        record.target(),
+        record.args()
+    );
+    for (key, value) in fields(record) {
+        line.push_str(&format!(" {}={}", key, value));
+    }
+    writeln!(std::io::stderr(), "{}", line).ok();
+}
+
+/// Key-value fields of the log record, e.g.
+/// `log::warn!(client_pid = pid; "...")`.
+pub(crate) fn fields(record: &log::Record) -> Vec<(String, String)> {
+    struct Collector(Vec<(String, String)>);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Collector {
+        fn visit_pair(
+            &mut self,
+            key: log::kv::Key<'kvs>,
+            value: log::kv::Value<'kvs>,
+        ) -> Result<(), log::kv::Error> {
+            self.0.push((key.to_string(), value.to_string()));
+            Ok(())
+        }
+    }
+
+    let mut collector = Collector(Vec::new());
+    record.key_values().visit(&mut collector).ok();
+    collector.0
+}
+
+pub(crate) fn json_line(record: &log::Record, time: SystemTime) -> String {
+    let fields: serde_json::Map<String, serde_json::Value> = fields(record)
+        .into_iter()
+        .map(|(k, v)| (k, serde_json::Value::String(v)))
+        .collect();
+    serde_json::json!({
+        "timestamp": format_rfc3339(time),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "module": record.module_path(),
+        "file": record.file(),
+        "line": record.line(),
+        "message": record.args().to_string(),
+        "fields": fields,
+    })
+    .to_string()
+}
+
+// Syslog severity of log level
+fn severity(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// Datagram of the native journald protocol. Key-value fields of the
+/// record are converted to upper case field names, e.g. `client_pid` to
+/// `CLIENT_PID`.
+pub(crate) fn journald_payload(record: &log::Record) -> Vec<u8> {
+    let mut payload = Vec::new();
+    let mut add_field = |name: &str, value: &str| {
+        payload.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            // Binary safe form: name, newline, little endian 64 bits
+            // length, value
+            payload.push(b'\n');
+            payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            payload.extend_from_slice(value.as_bytes());
+        } else {
+            payload.push(b'=');
+            payload.extend_from_slice(value.as_bytes());
+        }
+        payload.push(b'\n');
+    };
+    add_field("MESSAGE", &record.args().to_string());
+    add_field("PRIORITY", &severity(record.level()).to_string());
+    add_field("SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+    add_field("TARGET", record.target());
+    if let Some(file) = record.file() {
+        add_field("CODE_FILE", file);
+    }
+    if let Some(line) = record.line() {
+        add_field("CODE_LINE", &line.to_string());
+    }
+    for (key, value) in fields(record) {
+        if let Some(name) = journald_field_name(&key) {
+            add_field(&name, &value);
+        }
+    }
+    payload
+}
+
+// Journald field names only contain upper case letters, digits and
+// underscores, and cannot start with underscore which is reserved for
+// trusted fields.
+fn journald_field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches(|c: char| c == '_' || c.is_numeric());
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// RFC 5424 syslog message with key-value fields as structured data.
+pub(crate) fn syslog_message(
+    record: &log::Record,
+    time: SystemTime,
+    hostname: &str,
+    pid: u32,
+) -> String {
+    let fields = fields(record);
+    let structured_data = if fields.is_empty() {
+        "-".to_string()
+    } else {
+        let params: Vec<String> = fields
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "{}=\"{}\"",
+                    k,
+                    v.replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                        .replace(']', "\\]")
+                )
+            })
+            .collect();
+        format!("[{} {}]", SYSLOG_SD_ID, params.join(" "))
+    };
+    format!(
+        "<{}>1 {} {} {} {} - {} {}",
+        SYSLOG_FACILITY_DAEMON * 8 + severity(record.level()),
+        format_rfc3339(time),
+        if hostname.is_empty() { "-" } else { hostname },
+        SYSLOG_IDENTIFIER,
+        pid,
+        structured_data,
+        record.args()
+    )
+}
+
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    nix::unistd::gethostname(&mut buf)
+        .ok()
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use rabc::{ErrorKind, RabcClientInfo, RabcMessage, RabcStatus};
+
+use crate::command::RabcdCommand;
+use crate::limits::RabcdLimits;
+
+// Upper bounds in seconds of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.05, 0.1, 1.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    // Non-cumulative count of observations for each bucket of
+    // LATENCY_BUCKETS, the last one is for `+Inf`
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsData {
+    connections_accepted: u64,
+    connections_closed: u64,
+    connections_rejected: u64,
+    messages_received: BTreeMap<&'static str, u64>,
+    messages_sent: BTreeMap<&'static str, u64>,
+    messages_throttled: u64,
+    errors: BTreeMap<String, u64>,
+    bytes_received: u64,
+    bytes_sent: u64,
+    request_duration: Histogram,
+    clients: BTreeMap<u64, RabcClientInfo>,
+    next_client_id: u64,
+}
+
+/// Registry of the daemon metrics, also the data source of `status` request.
+#[derive(Debug)]
+pub(crate) struct RabcdMetrics {
+    start_time: Instant,
+    data: Mutex<MetricsData>,
+}
+
+impl Default for RabcdMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RabcdMetrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+            data: Mutex::new(MetricsData::default()),
+        }
+    }
+
+    /// Register new client and return its information.
+    pub(crate) fn client_connected(
+        &self,
+        uid: u32,
+        pid: Option<i32>,
+    ) -> RabcClientInfo {
+        let mut data = self.data.lock().expect("inner lock poisoned");
+        data.connections_accepted += 1;
+        data.next_client_id += 1;
+        let id = data.next_client_id;
+        let mut info = RabcClientInfo::default();
+        info.id = id;
+        info.pid = pid;
+        info.uid = uid;
+        info.connect_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        data.clients.insert(id, info.clone());
+        info
+    }
+
+    pub(crate) fn client_disconnected(&self, id: u64) {
+        let mut data = self.data.lock().expect("inner lock poisoned");
+        data.connections_closed += 1;
+        data.clients.remove(&id);
+    }
+
+    pub(crate) fn connection_rejected(&self) {
+        let mut data = self.data.lock().expect("inner lock poisoned");
+        data.connections_rejected += 1;
+        *data
+            .errors
+            .entry(ErrorKind::Throttled.to_string())
+            .or_default() += 1;
+    }
+
+    pub(crate) fn message_throttled(&self) {
+        self.data
+            .lock()
+            .expect("inner lock poisoned")
+            .messages_throttled += 1;
+    }
+
+    /// Record a request of specified size received from client.
+    pub(crate) fn message_received(&self, id: u64, request: &str, size: usize) {
+        // Unknown commands are counted as `other` to avoid unbounded label
+        // cardinality
+        let command = RabcdCommand::parse(request)
+            .map(|c| c.name())
+            .unwrap_or("other");
+        let mut data = self.data.lock().expect("inner lock poisoned");
+        *data.messages_received.entry(command).or_default() += 1;
+        data.bytes_received += size as u64;
+        if let Some(info) = data.clients.get_mut(&id) {
+            info.messages_received += 1;
+            info.bytes_received += size as u64;
+        }
+    }
+
+    /// Record a message of specified size sent to client.
+    pub(crate) fn message_sent(
+        &self,
+        id: u64,
+        message: &RabcMessage,
+        size: usize,
+    ) {
+        let mut data = self.data.lock().expect("inner lock poisoned");
+        let msg_type = match message {
+            RabcMessage::Reply(_) | RabcMessage::ReplyBytes(_) => "reply",
+            RabcMessage::Error(e) => {
+                *data.errors.entry(e.kind().to_string()).or_default() += 1;
+                "error"
+            }
+            RabcMessage::Notification(_) => "notification",
+            _ => "other",
+        };
+        *data.messages_sent.entry(msg_type).or_default() += 1;
+        data.bytes_sent += size as u64;
+        if let Some(info) = data.clients.get_mut(&id) {
+            info.messages_sent += 1;
+            info.bytes_sent += size as u64;
+        }
+    }
+
+    pub(crate) fn observe_request_duration(&self, duration: Duration) {
+        self.data
+            .lock()
+            .expect("inner lock poisoned")
+            .request_duration
+            .observe(duration.as_secs_f64());
+    }
+
+    pub(crate) fn status(&self, limits: &RabcdLimits) -> RabcStatus {
+        let data = self.data.lock().expect("inner lock poisoned");
+        let mut status = RabcStatus::default();
+        status.version = env!("CARGO_PKG_VERSION").to_string();
+        status.uptime = self.start_time.elapsed().as_secs();
+        status.connection_count = data.clients.len();
+        status.max_connections = limits.max_connections;
+        status.max_connections_per_uid = limits.max_connections_per_uid;
+        for info in data.clients.values() {
+            *status.connections_per_uid.entry(info.uid).or_default() += 1;
+        }
+        status.rejected_connection_count = data.connections_rejected;
+        status.throttled_message_count = data.messages_throttled;
+        status.clients = data.clients.values().cloned().collect();
+        status
+    }
+
+    /// Render all metrics in OpenMetrics text format.
+    pub(crate) fn to_openmetrics(&self) -> String {
+        let data = self.data.lock().expect("inner lock poisoned");
+        let mut ret = String::new();
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_uptime_seconds",
+            "gauge",
+            "Seconds since daemon started",
+        );
+        writeln!(
+            ret,
+            "rabcd_uptime_seconds {}",
+            self.start_time.elapsed().as_secs_f64()
+        )
+        .ok();
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_clients",
+            "gauge",
+            "Currently connected clients",
+        );
+        writeln!(ret, "rabcd_clients {}", data.clients.len()).ok();
+
+        for (name, help, value) in [
+            (
+                "rabcd_connections_accepted",
+                "Accepted client connections",
+                data.connections_accepted,
+            ),
+            (
+                "rabcd_connections_closed",
+                "Closed client connections",
+                data.connections_closed,
+            ),
+            (
+                "rabcd_connections_rejected",
+                "Client connections refused due to connection limits",
+                data.connections_rejected,
+            ),
+            (
+                "rabcd_messages_throttled",
+                "Messages refused due to message rate limit",
+                data.messages_throttled,
+            ),
+            (
+                "rabcd_received_bytes",
+                "Bytes received from clients",
+                data.bytes_received,
+            ),
+            ("rabcd_sent_bytes", "Bytes sent to clients", data.bytes_sent),
+        ] {
+            write_metric_header(&mut ret, name, "counter", help);
+            writeln!(ret, "{}_total {}", name, value).ok();
+        }
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_messages_received",
+            "counter",
+            "Messages received from clients by command",
+        );
+        for (command, count) in data.messages_received.iter() {
+            writeln!(
+                ret,
+                "rabcd_messages_received_total{{command=\"{}\"}} {}",
+                command, count
+            )
+            .ok();
+        }
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_messages_sent",
+            "counter",
+            "Messages sent to clients by type",
+        );
+        for (msg_type, count) in data.messages_sent.iter() {
+            writeln!(
+                ret,
+                "rabcd_messages_sent_total{{type=\"{}\"}} {}",
+                msg_type, count
+            )
+            .ok();
+        }
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_errors",
+            "counter",
+            "Errors by kind",
+        );
+        for (kind, count) in data.errors.iter() {
+            writeln!(ret, "rabcd_errors_total{{kind=\"{}\"}} {}", kind, count)
+                .ok();
+        }
+
+        write_metric_header(
+            &mut ret,
+            "rabcd_request_duration_seconds",
+            "histogram",
+            "Request handling latency",
+        );
+        let histogram = &data.request_duration;
+        let mut cumulative = 0u64;
+        for (i, count) in histogram.buckets.iter().enumerate() {
+            cumulative += count;
+            let bound = match LATENCY_BUCKETS.get(i) {
+                Some(b) => format_bucket_bound(*b),
+                None => "+Inf".to_string(),
+            };
+            writeln!(
+                ret,
+                "rabcd_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, cumulative
+            )
+            .ok();
+        }
+        writeln!(ret, "rabcd_request_duration_seconds_sum {}", histogram.sum)
+            .ok();
+        writeln!(
+            ret,
+            "rabcd_request_duration_seconds_count {}",
+            histogram.count
+        )
+        .ok();
+
+        ret.push_str("# EOF\n");
+        ret
+    }
+}
+
+// OpenMetrics requires canonical float with decimal point, e.g. `1.0`
+fn format_bucket_bound(bound: f64) -> String {
+    let ret = bound.to_string();
+    if ret.contains('.') {
+        ret
+    } else {
+        format!("{}.0", ret)
+    }
+}
+
+fn write_metric_header(
+    output: &mut String,
+    name: &str,
+    metric_type: &str,
+    help: &str,
+) {
+    writeln!(output, "# TYPE {} {}", name, metric_type).ok();
+    writeln!(output, "# HELP {} {}", name, help).ok();
+}
@@ -1,77 +1,600 @@
 // SPDX-License-Identifier: Apache-2.0
 
-use rabc::{ErrorKind, RabcConnection, SOCKET_PATH};
+mod command;
+mod config;
+mod daemon;
+mod exporter;
+mod limits;
+mod logger;
+mod metrics;
+mod unit_tests;
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
+
+use clap::Parser;
+use log::LevelFilter;
+use rabc::{
+    ErrorKind, RabcAsyncConnection, RabcError, RabcLogRecord, RabcMessage,
+    RabcNotification, IPC_HEADER_SIZE, IPC_PROTOCOL_VERSION, SOCKET_PATH,
+};
 use tokio::net::UnixListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::broadcast;
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() {
-    init_logger();
+use crate::command::{
+    parse_client_id, parse_log_level, parse_protocol_version, RabcdCommand,
+};
+use crate::config::RabcdConfig;
+use crate::daemon::{InstanceLock, ReadyNotifier, SocketFile};
+use crate::exporter::{MetricsListen, MetricsListener};
+use crate::limits::{
+    is_notification_visible, is_privileged, ConnectionGuard, ConnectionTracker,
+    RabcdLimits, TokenBucket,
+};
+use crate::logger::LogBackend;
+use crate::metrics::RabcdMetrics;
 
-    std::fs::remove_file(SOCKET_PATH).ok();
-    let listener = match UnixListener::bind(SOCKET_PATH) {
-        Ok(l) => l,
-        Err(e) => {
-            log::error!("Failed to bind UnixListener {}: {}", SOCKET_PATH, e);
-            return;
+const DEFAULT_MAX_CONNECTIONS: usize = 1024;
+const DEFAULT_MAX_CONNECTIONS_PER_UID: usize = 128;
+const DEFAULT_MAX_MESSAGE_RATE: u32 = 100;
+const DEFAULT_MAX_MESSAGE_BURST: u32 = 200;
+// Plain text replies without notifications or logs, used by librabc 0.1
+const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+/// Protocol version of client and messages it asked daemon to stream.
+#[derive(Debug)]
+struct ClientState {
+    /// [LEGACY_PROTOCOL_VERSION] until client stated its version
+    protocol_version: u32,
+    notifications: Option<broadcast::Receiver<RabcNotification>>,
+    logs: Option<broadcast::Receiver<RabcLogRecord>>,
+    log_level: LevelFilter,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "Rabc daemon")]
+struct Args {
+    /// UNIX socket path to listen on
+    #[arg(long, value_name = "PATH", default_value = SOCKET_PATH)]
+    socket: String,
+    /// Max count of connected clients, 0 means unlimited
+    #[arg(long, default_value_t = DEFAULT_MAX_CONNECTIONS)]
+    max_connections: usize,
+    /// Max count of connected clients for each user ID, 0 means unlimited
+    #[arg(long, default_value_t = DEFAULT_MAX_CONNECTIONS_PER_UID)]
+    max_connections_per_uid: usize,
+    /// Max messages per second for each client, 0 means unlimited
+    #[arg(long, default_value_t = DEFAULT_MAX_MESSAGE_RATE)]
+    max_message_rate: u32,
+    /// Max messages a client could send in a burst
+    #[arg(long, default_value_t = DEFAULT_MAX_MESSAGE_BURST)]
+    max_message_burst: u32,
+    /// Serve OpenMetrics on specified UNIX socket path
+    #[arg(long, value_name = "PATH", conflicts_with = "metrics_address")]
+    metrics_socket: Option<PathBuf>,
+    /// Serve OpenMetrics on specified loopback address, e.g. 127.0.0.1:9185
+    #[arg(long, value_name = "ADDRESS")]
+    metrics_address: Option<SocketAddr>,
+    /// Stay in foreground instead of daemonizing, default when started by
+    /// systemd
+    #[arg(long)]
+    foreground: bool,
+    /// Write PID of daemon to specified file
+    #[arg(long, value_name = "PATH")]
+    pid_file: Option<PathBuf>,
+    /// YAML configuration file
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Logging backend, default to journald when started by systemd,
+    /// syslog when daemonized, otherwise stderr
+    #[arg(long, value_enum)]
+    log_backend: Option<LogBackend>,
+    /// Log level and per-module filters, e.g. `info,rabcd::limits=debug`,
+    /// default to `RUST_LOG` environment variable or `rabc=info`
+    #[arg(long, value_name = "FILTER")]
+    log_filter: Option<String>,
+}
+
+impl Args {
+    fn metrics_listen(&self) -> Option<MetricsListen> {
+        if let Some(path) = self.metrics_socket.as_ref() {
+            Some(MetricsListen::Unix(path.clone()))
+        } else {
+            self.metrics_address.map(MetricsListen::Tcp)
         }
-    };
-    loop {
-        match listener.accept().await {
-            Ok((stream, _)) => {
-                tokio::spawn(async move {
-                    process_client(stream).await;
-                });
-            }
+    }
+}
+
+impl Args {
+    // Daemon changes working directory to `/`
+    fn make_paths_absolute(&mut self) -> Result<(), RabcError> {
+        self.socket = absolute_path(Path::new(&self.socket))?
+            .display()
+            .to_string();
+        if let Some(path) = self.pid_file.as_mut() {
+            *path = absolute_path(path.as_path())?;
+        }
+        if let Some(path) = self.metrics_socket.as_mut() {
+            *path = absolute_path(path.as_path())?;
+        }
+        Ok(())
+    }
+}
+
+fn absolute_path(path: &Path) -> Result<PathBuf, RabcError> {
+    std::path::absolute(path).map_err(|e| {
+        RabcError::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid path {}: {}", path.display(), e),
+        )
+    })
+}
+
+impl From<&Args> for RabcdLimits {
+    fn from(args: &Args) -> Self {
+        Self {
+            max_connections: args.max_connections,
+            max_connections_per_uid: args.max_connections_per_uid,
+            max_message_rate: args.max_message_rate,
+            max_message_burst: args.max_message_burst,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+    let under_systemd = daemon::is_under_systemd();
+    let foreground = args.foreground || under_systemd;
+    let config = match args.config.as_ref() {
+        Some(path) => match RabcdConfig::load(path) {
+            Ok(c) => c,
             Err(e) => {
-                log::error!("Failed to accept connection {}", e);
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
             }
+        },
+        None => RabcdConfig::default(),
+    };
+    let log_backend =
+        args.log_backend
+            .or(config.log.backend)
+            .unwrap_or(if under_systemd {
+                LogBackend::Journald
+            } else if foreground {
+                LogBackend::Stderr
+            } else {
+                LogBackend::Syslog
+            });
+    if let Err(e) = logger::init_logger(
+        log_backend,
+        args.log_filter.as_deref().or(config.log.filter.as_deref()),
+        !foreground,
+    ) {
+        eprintln!("{}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let result = args
+        .make_paths_absolute()
+        .and_then(|_| start(&args, foreground));
+    let (lock, notifier) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            log::error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Daemonize before creating the runtime as fork() is not safe once
+    // there are threads
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(r) => r,
+        Err(e) => {
+            log::error!("Failed to create tokio runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = runtime.block_on(run(&args, notifier));
+
+    // The lock file is kept, removing it could let two instances hold
+    // locks of different files with the same path.
+    if let Some(path) = args.pid_file.as_ref() {
+        std::fs::remove_file(path).ok();
+    }
+    drop(lock);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
         }
     }
 }
 
-fn init_logger() {
-    let mut log_builder = env_logger::Builder::new();
-    // TODO: Support changing logging level
-    log_builder.filter(Some("rabc"), log::LevelFilter::Debug);
-    log_builder.init();
+// Acquire the single-instance lock, then daemonize if requested and store
+// PID of the final process.
+fn start(
+    args: &Args,
+    foreground: bool,
+) -> Result<(InstanceLock, Option<ReadyNotifier>), RabcError> {
+    let mut lock =
+        InstanceLock::acquire(&daemon::lock_file_path(&args.socket))?;
+    let notifier = if foreground {
+        None
+    } else {
+        Some(daemon::daemonize()?)
+    };
+    lock.write_pid()?;
+    if let Some(path) = args.pid_file.as_ref() {
+        daemon::write_pid_file(path)?;
+    }
+    Ok((lock, notifier))
 }
 
-async fn process_client(stream: tokio::net::UnixStream) {
-    log::debug!("new client connected!");
-    let std_stream = match stream.into_std() {
-        Ok(s) => s,
+async fn run(
+    args: &Args,
+    notifier: Option<ReadyNotifier>,
+) -> Result<(), RabcError> {
+    let metrics = Arc::new(RabcdMetrics::new());
+    let tracker = Arc::new(ConnectionTracker::new(
+        RabcdLimits::from(args),
+        metrics.clone(),
+    ));
+
+    if let Some(listen) = args.metrics_listen() {
+        let listener = MetricsListener::bind(&listen).await?;
+        tokio::spawn(listener.serve(metrics));
+    }
+
+    // Stale socket left by crashed instance, safe to remove as we are
+    // holding the lock
+    daemon::remove_stale_socket(Path::new(&args.socket))?;
+    let listener = UnixListener::bind(&args.socket).map_err(|e| {
+        RabcError::new(
+            ErrorKind::Bug,
+            format!("Failed to bind UnixListener {}: {}", args.socket, e),
+        )
+    })?;
+    // Removed once this function returns
+    let _socket_file = SocketFile::new(Path::new(&args.socket))?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+
+    if let Some(notifier) = notifier {
+        notifier.ready()?;
+        logger::stop_copy_to_stderr();
+    }
+    log::info!("Listening on {}", args.socket);
+    loop {
+        tokio::select! {
+            result = listener.accept() => match result {
+                Ok((stream, _)) => {
+                    let tracker = tracker.clone();
+                    tokio::spawn(async move {
+                        process_client(stream, tracker).await;
+                    });
+                }
+                Err(e) => {
+                    log::error!("Failed to accept connection {}", e);
+                }
+            },
+            _ = sigterm.recv() => break,
+            _ = sigint.recv() => break,
+        }
+    }
+    log::info!("Exiting on signal");
+    Ok(())
+}
+
+async fn process_client(
+    stream: tokio::net::UnixStream,
+    tracker: Arc<ConnectionTracker>,
+) {
+    let (uid, pid) = match stream.peer_cred() {
+        Ok(cred) => (cred.uid(), cred.pid()),
         Err(e) => {
-            log::error!(
-                "Failed to convert tokio::net::UnixStream to std: {}",
-                e
-            );
+            log::error!("Failed to get peer credentials of client: {}", e);
             return;
         }
     };
-    let mut conn = match RabcConnection::new(std_stream) {
-        Ok(c) => c,
+    let mut conn = RabcAsyncConnection::new(stream);
+    let mut guard = match tracker.connect(uid, pid) {
+        Ok(g) => g,
         Err(e) => {
-            log::error!("Failed to setup connect to client: {}", e);
+            log::warn!(
+                client_pid = pid, error_kind:% = e.kind();
+                "Refusing client of user ID {}: {}", uid, e
+            );
+            if let Err(e) = conn.ipc_send_message(&RabcMessage::Error(e)).await
+            {
+                log::error!(
+                    client_pid = pid, error_kind:% = e.kind();
+                    "Failed to send error to client: {}", e
+                );
+            }
             return;
         }
     };
+    log::debug!(client_pid = pid; "new client {} connected!", guard.id());
+    let limits = tracker.limits();
+    let mut bucket =
+        TokenBucket::new(limits.max_message_rate, limits.max_message_burst);
+    let mut state = ClientState {
+        protocol_version: LEGACY_PROTOCOL_VERSION,
+        notifications: None,
+        logs: None,
+        log_level: LevelFilter::Off,
+    };
     loop {
-        match conn.ipc_recv() {
-            Ok(content) => {
-                log::debug!("Got content from client '{}'", content);
-                if let Err(e) = conn.ipc_send("pong") {
-                    log::error!("Failed to send to client: {}", e);
+        tokio::select! {
+            result = conn.ipc_recv() => match result {
+                Ok(content) => {
+                    log::debug!(
+                        client_pid = pid;
+                        "Got content from client '{}'", content
+                    );
+                    let start_time = Instant::now();
+                    tracker.metrics().message_received(
+                        guard.id(),
+                        &content,
+                        content.len() + IPC_HEADER_SIZE,
+                    );
+                    let message = if !bucket.try_take() {
+                        tracker.metrics().message_throttled();
+                        RabcMessage::Error(RabcError::new(
+                            ErrorKind::Throttled,
+                            format!(
+                                "Exceeded the max message rate {} per second",
+                                limits.max_message_rate
+                            ),
+                        ))
+                    } else {
+                        match handle_request(
+                            &content,
+                            &tracker,
+                            &guard,
+                            &mut state,
+                        ) {
+                            Ok(r) => RabcMessage::Reply(r),
+                            Err(e) => RabcMessage::Error(e),
+                        }
+                    };
+                    if let Err(e) = send_message(
+                        &mut conn, &tracker, &guard, &state, &message,
+                    )
+                    .await
+                    {
+                        log::error!(
+                            client_pid = pid, error_kind:% = e.kind();
+                            "Failed to send to client: {}", e
+                        );
+                    }
+                    tracker
+                        .metrics()
+                        .observe_request_duration(start_time.elapsed());
+                }
+                Err(e) => {
+                    if e.kind().is_connection_error() {
+                        // Client disconnected
+                        log::debug!(
+                            client_pid = pid;
+                            "client {} disconnected!", guard.id()
+                        );
+                    } else {
+                        log::error!(
+                            client_pid = pid, error_kind:% = e.kind();
+                            "Failed to recv from client: {}", e
+                        );
+                        // Tell client why the connection is closed, e.g.
+                        // invalid data or oversized message
+                        let message = RabcMessage::Error(e);
+                        send_message(
+                            &mut conn, &tracker, &guard, &state, &message,
+                        )
+                        .await
+                        .ok();
+                    }
+                    break;
+                }
+            },
+            Some(notification) =
+                recv_broadcast(&mut state.notifications, true) =>
+            {
+                if !is_notification_visible(&notification, guard.uid()) {
+                    continue;
+                }
+                let message = RabcMessage::Notification(notification);
+                if let Err(e) = send_message(
+                    &mut conn, &tracker, &guard, &state, &message,
+                )
+                .await
+                {
+                    log::error!(
+                        client_pid = pid, error_kind:% = e.kind();
+                        "Failed to send notification: {}", e
+                    );
                 }
             }
-            Err(e) => {
-                if e.kind() == ErrorKind::IpcConnectionError {
-                    // Client disconnected
-                    log::debug!("client disconnected!");
-                } else {
-                    log::error!("Failed to recv from client: {}", e);
+            Some(record) =
+                recv_broadcast(&mut state.logs, false) =>
+            {
+                if record.level <= state.log_level {
+                    // Not logging the failure as it would be forwarded
+                    // again
+                    let message = RabcMessage::Log(record);
+                    send_message(
+                        &mut conn, &tracker, &guard, &state, &message,
+                    )
+                    .await
+                    .ok();
+                }
+            }
+            Some(message) = guard.recv_control() => {
+                if let Err(e) = send_message(
+                    &mut conn, &tracker, &guard, &state, &message,
+                )
+                .await
+                {
+                    log::error!(
+                        client_pid = pid, error_kind:% = e.kind();
+                        "Failed to send to client: {}", e
+                    );
+                }
+            }
+        }
+    }
+    if state.logs.is_some() {
+        logger::forward_logs(guard.id(), LevelFilter::Off);
+    }
+}
+
+fn handle_request(
+    request: &str,
+    tracker: &ConnectionTracker,
+    guard: &ConnectionGuard,
+    state: &mut ClientState,
+) -> Result<String, RabcError> {
+    let mut args = request.split_whitespace().skip(1);
+    match RabcdCommand::parse(request)? {
+        RabcdCommand::Ping => Ok("pong".to_string()),
+        RabcdCommand::Protocol => {
+            let version = parse_protocol_version(args.next())?;
+            state.protocol_version = version.min(IPC_PROTOCOL_VERSION);
+            Ok(state.protocol_version.to_string())
+        }
+        RabcdCommand::Status => tracker.status_for(guard.uid()).to_json(),
+        RabcdCommand::Version => Ok(env!("CARGO_PKG_VERSION").to_string()),
+        RabcdCommand::Subscribe => {
+            state.notifications = Some(tracker.subscribe());
+            Ok("subscribed".to_string())
+        }
+        RabcdCommand::Unsubscribe => {
+            state.notifications = None;
+            Ok("unsubscribed".to_string())
+        }
+        // Advertised command names as JSON array, used by client for
+        // completion
+        RabcdCommand::Commands => Ok(serde_json::to_string(
+            &RabcdCommand::ALL
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>(),
+        )?),
+        RabcdCommand::Logs => {
+            let level = parse_log_level(args.next())?;
+            // Daemon logs include the pid and uid of other clients which
+            // are hidden from unprivileged users like status and
+            // notifications do
+            if level != LevelFilter::Off {
+                check_log_permission(guard.uid())?;
+            }
+            state.log_level = level;
+            state.logs = logger::forward_logs(guard.id(), level);
+            Ok(if level == LevelFilter::Off {
+                "stopped streaming logs".to_string()
+            } else {
+                format!("streaming logs up to {}", level)
+            })
+        }
+        RabcdCommand::ClientLogLevel => {
+            let id = parse_client_id(args.next())?;
+            let level = parse_log_level(args.next())?;
+            tracker.send_to_client(
+                guard.uid(),
+                id,
+                RabcMessage::SetLogLevel(level),
+            )?;
+            log::info!(
+                "Client {} log level changed to {} by client {}",
+                id,
+                level,
+                guard.id()
+            );
+            Ok(format!("log level of client {} changed to {}", id, level))
+        }
+    }
+}
+
+// Only root or the user running daemon could stream daemon logs
+fn check_log_permission(uid: u32) -> Result<(), RabcError> {
+    if !is_privileged(uid) {
+        let daemon_uid = nix::unistd::getuid().as_raw();
+        return Err(RabcError::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "Only root or user {} running rabcd is permitted to \
+                 stream daemon logs",
+                daemon_uid
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Legacy client only understands plain text reply, other messages are
+// dropped.
+fn legacy_data(message: &RabcMessage) -> Option<String> {
+    match message {
+        RabcMessage::Reply(reply) => Some(reply.clone()),
+        RabcMessage::ReplyBytes(data) => {
+            Some(String::from_utf8_lossy(data).to_string())
+        }
+        RabcMessage::Error(e) => Some(e.to_string()),
+        _ => None,
+    }
+}
+
+// Wait for next broadcast message, never resolves if not subscribed.
+// The lag is not logged when `warn_lag` is false, e.g. for log receiver
+// which would get the warning forwarded back.
+async fn recv_broadcast<T: Clone>(
+    receiver: &mut Option<broadcast::Receiver<T>>,
+    warn_lag: bool,
+) -> Option<T> {
+    let receiver = match receiver.as_mut() {
+        Some(r) => r,
+        None => return std::future::pending().await,
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(n) => return Some(n),
+            Err(broadcast::error::RecvError::Lagged(count)) => {
+                if warn_lag {
+                    log::warn!("Client lagged, dropped {} messages", count);
                 }
-                break;
             }
+            Err(broadcast::error::RecvError::Closed) => return None,
         }
     }
 }
+
+async fn send_message(
+    conn: &mut RabcAsyncConnection,
+    tracker: &ConnectionTracker,
+    guard: &ConnectionGuard,
+    state: &ClientState,
+    message: &RabcMessage,
+) -> Result<(), RabcError> {
+    let data = if state.protocol_version == LEGACY_PROTOCOL_VERSION {
+        match legacy_data(message) {
+            Some(d) => d,
+            None => return Ok(()),
+        }
+    } else {
+        message.to_json()?
+    };
+    conn.ipc_send(&data).await?;
+    tracker.metrics().message_sent(
+        guard.id(),
+        message,
+        data.len() + IPC_HEADER_SIZE,
+    );
+    Ok(())
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::ErrorKind;
+
+use crate::daemon::{lock_file_path, write_pid_file, InstanceLock};
+
+#[test]
+fn test_second_instance_lock_refused() {
+    let socket = std::env::temp_dir()
+        .join(format!("rabcd_lock_test_{}", std::process::id()));
+    let path = lock_file_path(&socket.display().to_string());
+
+    let mut lock = InstanceLock::acquire(&path).unwrap();
+    lock.write_pid().unwrap();
+    let e = InstanceLock::acquire(&path).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    assert!(e.msg().contains(&std::process::id().to_string()));
+
+    drop(lock);
+    assert!(InstanceLock::acquire(&path).is_ok());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn test_lock_and_pid_file_not_following_symlink() {
+    let dir = std::env::temp_dir();
+    let target =
+        dir.join(format!("rabcd_symlink_target_{}", std::process::id()));
+    let link = dir.join(format!("rabcd_symlink_test_{}", std::process::id()));
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    assert!(InstanceLock::acquire(&link).is_err());
+    assert!(write_pid_file(&link).is_err());
+    assert!(!target.exists());
+    std::fs::remove_file(&link).unwrap();
+}
@@ -0,0 +1,27 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use rabc::ErrorKind;
+
+use crate::exporter::{MetricsListen, MetricsListener};
+
+#[test]
+fn test_metrics_socket_not_removing_other_file() {
+    let path = std::env::temp_dir()
+        .join(format!("rabcd_metrics_test_{}", std::process::id()));
+    std::fs::write(&path, "not a socket").unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    let listen = MetricsListen::Unix(path.clone());
+
+    let result = runtime.block_on(MetricsListener::bind(&listen));
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidArgument);
+    assert!(path.exists());
+
+    std::fs::remove_file(&path).unwrap();
+    runtime.block_on(MetricsListener::bind(&listen)).unwrap();
+    // Stale socket of previous run is replaced
+    runtime.block_on(MetricsListener::bind(&listen)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
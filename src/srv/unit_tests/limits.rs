@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rabc::{ErrorKind, RabcMessage, RabcNotification};
+
+use crate::limits::{
+    is_notification_visible, is_privileged, ConnectionTracker, RabcdLimits,
+    TokenBucket,
+};
+use crate::metrics::RabcdMetrics;
+
+#[test]
+fn test_token_bucket_burst_and_refill() {
+    let mut bucket = TokenBucket::new(10, 3);
+    let now = Instant::now();
+
+    assert!(bucket.try_take_at(now));
+    assert!(bucket.try_take_at(now));
+    assert!(bucket.try_take_at(now));
+    assert!(!bucket.try_take_at(now));
+
+    // 10 messages per second, a token is refilled in 100 milliseconds
+    assert!(bucket.try_take_at(now + Duration::from_millis(100)));
+    assert!(!bucket.try_take_at(now + Duration::from_millis(100)));
+}
+
+#[test]
+fn test_token_bucket_unlimited() {
+    let mut bucket = TokenBucket::new(0, 0);
+    let now = Instant::now();
+    for _ in 0..1000 {
+        assert!(bucket.try_take_at(now));
+    }
+}
+
+#[test]
+fn test_connection_limit_per_uid() {
+    let tracker = Arc::new(ConnectionTracker::new(
+        RabcdLimits {
+            max_connections: 3,
+            max_connections_per_uid: 2,
+            ..Default::default()
+        },
+        Arc::new(RabcdMetrics::new()),
+    ));
+
+    let _conn1 = tracker.connect(1000, None).unwrap();
+    let conn2 = tracker.connect(1000, None).unwrap();
+    let e = tracker.connect(1000, None).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Throttled);
+
+    let _conn3 = tracker.connect(0, None).unwrap();
+    let e = tracker.connect(0, None).unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Throttled);
+
+    drop(conn2);
+    let status = tracker.status();
+    assert_eq!(status.connection_count, 2);
+    assert_eq!(status.connections_per_uid.get(&1000), Some(&1));
+    assert_eq!(status.rejected_connection_count, 2);
+    assert!(tracker.connect(1000, None).is_ok());
+}
+
+#[test]
+fn test_send_to_client_permission() {
+    let tracker = Arc::new(ConnectionTracker::new(
+        RabcdLimits::default(),
+        Arc::new(RabcdMetrics::new()),
+    ));
+    let mut conn = tracker.connect(1000, None).unwrap();
+    let message = || RabcMessage::SetLogLevel(log::LevelFilter::Debug);
+
+    let e = tracker
+        .send_to_client(1001, conn.id(), message())
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::PermissionDenied);
+    let e = tracker
+        .send_to_client(1000, conn.id() + 1, message())
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+
+    tracker.send_to_client(1000, conn.id(), message()).unwrap();
+    tracker.send_to_client(0, conn.id(), message()).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    assert_eq!(runtime.block_on(conn.recv_control()), Some(message()));
+}
+
+#[test]
+fn test_other_users_hidden_from_unprivileged() {
+    let tracker = Arc::new(ConnectionTracker::new(
+        RabcdLimits::default(),
+        Arc::new(RabcdMetrics::new()),
+    ));
+    let mut notifications = tracker.subscribe();
+    // Not the user running test, hence unprivileged
+    let (uid, other_uid) = (60001, 60002);
+    assert!(!is_privileged(uid));
+    let _conn1 = tracker.connect(uid, Some(100)).unwrap();
+    let _conn2 = tracker.connect(other_uid, Some(200)).unwrap();
+
+    let status = tracker.status_for(uid);
+    assert_eq!(status.connection_count, 2);
+    assert_eq!(status.connections_per_uid.len(), 1);
+    assert_eq!(status.clients.len(), 1);
+    assert_eq!(status.clients[0].pid, Some(100));
+    assert_eq!(tracker.status_for(0).clients.len(), 2);
+
+    let connected = notifications.try_recv().unwrap();
+    assert!(is_notification_visible(&connected, uid));
+    let connected = notifications.try_recv().unwrap();
+    assert!(matches!(
+        connected,
+        RabcNotification::ClientConnected { .. }
+    ));
+    assert!(!is_notification_visible(&connected, uid));
+    assert!(is_notification_visible(&connected, 0));
+}
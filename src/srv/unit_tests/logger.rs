@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::RabcdConfig;
+use crate::logger::{journald_payload, json_line, syslog_message, LogBackend};
+
+fn test_time() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1656662400) + Duration::from_micros(123)
+}
+
+#[test]
+fn test_journald_structured_fields() {
+    let kvs = [("client_pid", 1234), ("error_kind", 0)];
+    let payload = journald_payload(
+        &log::Record::builder()
+            .args(format_args!("first\nsecond"))
+            .level(log::Level::Warn)
+            .target("rabcd")
+            .key_values(&kvs)
+            .build(),
+    );
+    let mut expected_message = b"MESSAGE\n".to_vec();
+    expected_message.extend_from_slice(&12u64.to_le_bytes());
+    expected_message.extend_from_slice(b"first\nsecond\n");
+    assert!(payload.starts_with(&expected_message));
+    let payload = String::from_utf8_lossy(&payload);
+    assert!(payload.contains("\nPRIORITY=4\n"));
+    assert!(payload.contains("\nSYSLOG_IDENTIFIER=rabcd\n"));
+    assert!(payload.contains("\nCLIENT_PID=1234\n"));
+    assert!(payload.contains("\nERROR_KIND=0\n"));
+}
+
+#[test]
+fn test_syslog_rfc5424() {
+    let kvs = [("client_pid", "12\"3")];
+    let record = log::Record::builder()
+        .args(format_args!("hello"))
+        .level(log::Level::Error)
+        .target("rabcd")
+        .key_values(&kvs)
+        .build();
+    assert_eq!(
+        syslog_message(&record, test_time(), "host", 99),
+        "<27>1 2022-07-01T08:00:00.000123Z host rabcd 99 - \
+         [rabcd@32473 client_pid=\"12\\\"3\"] hello"
+    );
+    let record = log::Record::builder()
+        .args(format_args!("hello"))
+        .level(log::Level::Info)
+        .build();
+    assert_eq!(
+        syslog_message(&record, test_time(), "", 99),
+        "<30>1 2022-07-01T08:00:00.000123Z - rabcd 99 - - hello"
+    );
+}
+
+#[test]
+fn test_json_line() {
+    let kvs = [("client_pid", 1234)];
+    let record = log::Record::builder()
+        .args(format_args!("hello"))
+        .level(log::Level::Debug)
+        .target("rabcd::limits")
+        .file(Some("limits.rs"))
+        .line(Some(10))
+        .key_values(&kvs)
+        .build();
+    let value: serde_json::Value =
+        serde_json::from_str(&json_line(&record, test_time())).unwrap();
+    assert_eq!(value["timestamp"], "2022-07-01T08:00:00.000123Z");
+    assert_eq!(value["level"], "DEBUG");
+    assert_eq!(value["target"], "rabcd::limits");
+    assert_eq!(value["line"], 10);
+    assert_eq!(value["message"], "hello");
+    assert_eq!(value["fields"]["client_pid"], "1234");
+}
+
+#[test]
+fn test_log_config() {
+    let config = RabcdConfig::from_yaml(
+        "log:\n  backend: syslog\n  filter: info,rabcd::limits=debug\n",
+    )
+    .unwrap();
+    assert_eq!(config.log.backend, Some(LogBackend::Syslog));
+    assert_eq!(
+        config.log.filter.as_deref(),
+        Some("info,rabcd::limits=debug")
+    );
+    assert!(RabcdConfig::from_yaml("log:\n  level: info\n").is_err());
+}
@@ -0,0 +1,82 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use std::time::Duration;
+
+use rabc::{ErrorKind, RabcError, RabcMessage};
+
+use crate::limits::RabcdLimits;
+use crate::metrics::RabcdMetrics;
+
+#[test]
+fn test_client_message_counters() {
+    let metrics = RabcdMetrics::new();
+
+    let id = metrics.client_connected(1000, Some(100)).id;
+    metrics.message_received(id, "ping", 12);
+    metrics.message_sent(id, &RabcMessage::Reply("pong".to_string()), 20);
+    metrics.message_sent(id, &RabcMessage::Reply("pong".to_string()), 20);
+
+    let status = metrics.status(&RabcdLimits::default());
+    assert_eq!(status.connection_count, 1);
+    assert_eq!(status.connections_per_uid.get(&1000), Some(&1));
+    assert_eq!(status.clients.len(), 1);
+    let info = &status.clients[0];
+    assert_eq!(info.id, id);
+    assert_eq!(info.pid, Some(100));
+    assert_eq!(info.uid, 1000);
+    assert_eq!(info.messages_received, 1);
+    assert_eq!(info.bytes_received, 12);
+    assert_eq!(info.messages_sent, 2);
+    assert_eq!(info.bytes_sent, 40);
+
+    metrics.client_disconnected(id);
+    assert!(metrics.status(&RabcdLimits::default()).clients.is_empty());
+}
+
+#[test]
+fn test_openmetrics_output() {
+    let metrics = RabcdMetrics::new();
+
+    let id = metrics.client_connected(1000, None).id;
+    metrics.message_received(id, "ping", 12);
+    metrics.message_received(id, "no_such_command", 23);
+    metrics.message_sent(
+        id,
+        &RabcMessage::Error(RabcError::new(
+            ErrorKind::Throttled,
+            "throttled".to_string(),
+        )),
+        30,
+    );
+    metrics.observe_request_duration(Duration::from_micros(50));
+    metrics.observe_request_duration(Duration::from_millis(20));
+    metrics.observe_request_duration(Duration::from_secs(5));
+
+    let output = metrics.to_openmetrics();
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert!(lines.contains(&"rabcd_clients 1"));
+    assert!(lines.contains(&"rabcd_connections_accepted_total 1"));
+    assert!(lines.contains(&"rabcd_received_bytes_total 35"));
+    assert!(
+        lines.contains(&"rabcd_messages_received_total{command=\"ping\"} 1")
+    );
+    assert!(
+        lines.contains(&"rabcd_messages_received_total{command=\"other\"} 1")
+    );
+    assert!(lines.contains(&"rabcd_messages_sent_total{type=\"error\"} 1"));
+    assert!(lines.contains(&"rabcd_errors_total{kind=\"Throttled\"} 1"));
+    assert!(lines
+        .contains(&"rabcd_request_duration_seconds_bucket{le=\"0.0001\"} 1"));
+    assert!(
+        lines.contains(&"rabcd_request_duration_seconds_bucket{le=\"0.05\"} 2")
+    );
+    assert!(
+        lines.contains(&"rabcd_request_duration_seconds_bucket{le=\"1.0\"} 2")
+    );
+    assert!(
+        lines.contains(&"rabcd_request_duration_seconds_bucket{le=\"+Inf\"} 3")
+    );
+    assert!(lines.contains(&"rabcd_request_duration_seconds_count 3"));
+    assert_eq!(lines.last(), Some(&"# EOF"));
+}
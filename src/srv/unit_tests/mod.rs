@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: Apache-2.0
+
+#[cfg(test)]
+mod daemon;
+#[cfg(test)]
+mod exporter;
+#[cfg(test)]
+mod limits;
+#[cfg(test)]
+mod logger;
+#[cfg(test)]
+mod metrics;
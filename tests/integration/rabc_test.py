@@ -1,23 +1,42 @@
 # SPDX-License-Identifier: Apache-2.0
 
+import asyncio
 import logging
 import os
 import signal
+import socket
+import struct
 import subprocess
 import sys
+import threading
 import time
 
 import pytest
 
+from rabc import ErrorKind
+from rabc import RabcBugError
 from rabc import RabcClient
+from rabc import RabcClientConnected
+from rabc import RabcConnectionError
+from rabc import RabcError
+from rabc import RabcEvent
+from rabc import RabcIpcConnectionError
+from rabc import RabcNotConnectedError
+from rabc import RabcProtocolError
+from rabc import RabcStatus
+import rabc
+import rabc.aio
 
 DAEMON_MAX_WAIT_TIME = 50   # 5 seconds
+VERSION_FILE = os.path.join(
+    os.path.dirname(__file__), "..", "..", "VERSION"
+)
 
 
 @pytest.fixture(scope="session", autouse=True)
 def rabc_daemon():
     daemon = subprocess.Popen(
-        "rabcd",
+        ["rabcd", "--foreground"],
         stdout=subprocess.PIPE,
         stderr=subprocess.PIPE,
         preexec_fn=os.setsid,
@@ -48,3 +67,137 @@ def test_client():
             if reply:
                 reply_count += 1
                 logging.info(f"Got reply from rabcd '{reply}'")
+
+
+def test_log_record_keeps_rust_location(caplog):
+    caplog.set_level(logging.DEBUG)
+    client = RabcClient()
+    for event in client.poll(5):
+        client.process(event)
+    records = [r for r in caplog.records if r.name.startswith("rabc.")]
+    assert records
+    for record in records:
+        assert record.pathname.endswith(".rs")
+        assert record.lineno > 0
+        assert record.rabc_thread
+
+
+def test_client_bytes_reply():
+    client = RabcClient()
+    reply = None
+    while reply is None:
+        for event in client.poll(5):
+            reply = client.process_bytes(event)
+    assert reply == b"pong"
+
+
+def start_fake_daemon(socket_path, messages):
+    """
+    Reply the `protocol` request of the first client and send it specified
+    raw messages, return the thread and listening socket.
+    """
+    server = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    server.bind(socket_path)
+    server.listen(1)
+
+    def fake_daemon():
+        conn, _ = server.accept()
+        with conn:
+            (size,) = struct.unpack("N", conn.recv(struct.calcsize("N")))
+            assert conn.recv(size) == b"protocol 2"
+            for message in [b'{"reply": "2"}'] + messages:
+                conn.sendall(struct.pack("N", len(message)) + message)
+            conn.recv(1)
+
+    thread = threading.Thread(target=fake_daemon)
+    thread.start()
+    return thread, server
+
+
+def test_client_binary_reply(tmp_path):
+    socket_path = str(tmp_path / "rabc.sock")
+    thread, server = start_fake_daemon(
+        socket_path, [b'{"reply_bytes": [255, 254]}'] * 3
+    )
+    with RabcClient(socket_path) as client:
+        assert client.process_bytes(RabcEvent.IPC_IN) == b"\xff\xfe"
+        with pytest.raises(RabcProtocolError):
+            client.process(RabcEvent.IPC_IN)
+        reply = client.process_message(RabcEvent.IPC_IN)
+        assert reply.result_bytes() == b"\xff\xfe"
+        with pytest.raises(RabcProtocolError):
+            reply.result()
+    thread.join()
+    server.close()
+
+
+def test_connection_errors(tmp_path):
+    with pytest.raises(RabcNotConnectedError) as e:
+        RabcClient(str(tmp_path / "no_daemon"))
+    assert isinstance(e.value, RabcConnectionError)
+    assert e.value.is_connection_error
+
+    not_dir = tmp_path / "file"
+    not_dir.write_text("")
+    with pytest.raises(RabcIpcConnectionError) as e:
+        RabcClient(str(not_dir / "sock"))
+    assert isinstance(e.value, RabcConnectionError)
+
+
+def test_process_invalid_event():
+    client = RabcClient()
+    with pytest.raises(RabcBugError) as e:
+        client.process(RabcEvent.TIMER + 100)
+    assert e.value.kind == ErrorKind.BUG
+
+
+def test_aio_client():
+    async def run():
+        async with rabc.aio.RabcClient() as client:
+            notifications = client.notifications()
+            # Subscribe before other client connecting
+            first = asyncio.ensure_future(notifications.__anext__())
+            await asyncio.sleep(0.5)
+            async with rabc.aio.RabcClient() as other:
+                assert await other.request("ping") == "pong"
+                assert await other.request_bytes("ping") == b"pong"
+            notification = await first
+            assert isinstance(notification, RabcClientConnected)
+            assert isinstance(await client.request("status"), RabcStatus)
+            with pytest.raises(RabcError):
+                await client.request("no_such_command")
+
+    asyncio.run(run())
+
+
+def test_aio_client_binary_reply(tmp_path):
+    socket_path = str(tmp_path / "rabc.sock")
+    thread, server = start_fake_daemon(
+        socket_path, [b'{"reply_bytes": [255, 254]}']
+    )
+
+    async def run():
+        async with rabc.aio.RabcClient(socket_path) as client:
+            assert await client.request_bytes("ping") == b"\xff\xfe"
+
+    asyncio.run(run())
+    thread.join()
+    server.close()
+
+
+def test_client_close():
+    with RabcClient() as client:
+        client.send("status")
+        reply = None
+        while reply is None or reply.heartbeat:
+            for event in client.poll(5):
+                assert isinstance(event, RabcEvent)
+                reply = client.process_message(event)
+        assert reply.result().connection_count > 0
+    with pytest.raises(RabcNotConnectedError):
+        client.poll(0)
+
+
+def test_package_version():
+    with open(VERSION_FILE) as fd:
+        assert rabc.__version__ == fd.read().strip()